@@ -0,0 +1,69 @@
+//! Timings of output formats on a large generated sheet, run by `cargo bench --bench rows`.
+//!
+//! The workbook is converted by `--bench-all` of the built binary, which prints the fastest of its runs.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const ROWS: usize = 300_000;
+
+/// Write a workbook of a single `Data` sheet with a header and `rows` rows of numbers and strings.
+fn generate(path: &Path, rows: usize) -> zip::result::ZipResult<()> {
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path)?);
+    let options = zip::write::SimpleFileOptions::default();
+    let parts = [
+        (
+            "[Content_Types].xml",
+            r#"<?xml version="1.0" encoding="UTF-8"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#,
+        ),
+        (
+            "_rels/.rels",
+            r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#,
+        ),
+        (
+            "xl/workbook.xml",
+            r#"<?xml version="1.0" encoding="UTF-8"?><workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Data" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+        ),
+        (
+            "xl/_rels/workbook.xml.rels",
+            r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#,
+        ),
+    ];
+    for (name, xml) in parts {
+        zip.start_file(name, options)?;
+        zip.write_all(xml.as_bytes())?;
+    }
+    zip.start_file("xl/worksheets/sheet1.xml", options)?;
+    zip.write_all(br#"<?xml version="1.0" encoding="UTF-8"?><worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#)?;
+    let text = |s: &str| format!(r#"<c t="inlineStr"><is><t>{}</t></is></c>"#, s);
+    let header: String = ["id", "name", "score", "note"]
+        .iter()
+        .map(|s| text(s))
+        .collect();
+    write!(zip, "<row>{}</row>", header)?;
+    for i in 1..=rows {
+        write!(
+            zip,
+            "<row><c><v>{}</v></c>{}<c><v>{}</v></c>{}</row>",
+            i,
+            text(&format!("name {}", i)),
+            i as f64 / 8.0,
+            text(if i % 3 == 0 { "a, \"quoted\" note" } else { "" }),
+        )?;
+    }
+    zip.write_all(b"</sheetData></worksheet>")?;
+    zip.finish()?;
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("bench_rows.xlsx");
+    generate(&path, ROWS)?;
+    let output = Command::new(env!("CARGO_BIN_EXE_xlsx2csv"))
+        .arg(&path)
+        .arg("--bench-all")
+        .output()?;
+    std::io::stderr().write_all(&output.stderr)?;
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::time::{Duration, Instant};
+
+use crate::output::RawLines;
+use crate::progress::Progress;
+use crate::summary::print_table;
+use crate::workbook::Workbook;
+use crate::writer::SheetWriter;
+use crate::{csv_writer, html, json, write_sheet, Opt};
+
+/// Runs of each format, the fastest one is reported.
+const RUNS: usize = 3;
+
+fn fastest<F: FnMut() -> Result<(), String>>(mut run: F) -> Result<Duration, String> {
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        run()?;
+        best = best.min(start.elapsed());
+    }
+    Ok(best)
+}
+
+/// Convert a sheet to each enabled format into null sinks, and print the timings to stderr.
+pub fn bench_all(workbook: &mut Workbook, sheet: &str, opt: &Opt) -> Result<(), String> {
+    let start = Instant::now();
+    let range = workbook.range(sheet).map_err(|e| e.to_string())?;
+    let parse = start.elapsed();
+
+    let mut timings: Vec<(&str, Duration)> = Vec::new();
+    let csv = fastest(|| {
+        let mut wtr = csv_writer(opt)
+            .map_err(|e| e.to_string())?
+            .from_writer(RawLines::new(std::io::sink()));
+        write_sheet(workbook, sheet, &mut wtr, opt, None, &Progress::disabled())
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    })?;
+    timings.push(("csv", csv));
+    let html = fastest(|| {
+        let mut html = html::HtmlWriter::new(std::io::sink()).map_err(|e| e.to_string())?;
+        html.write_sheet(sheet, &range, opt)
+            .map_err(|e| e.to_string())?;
+        html.finish().map_err(|e| e.to_string())
+    })?;
+    timings.push(("html", html));
+    let json = fastest(|| {
+        let mut json = json::JsonWorkbookWriter::new(std::io::sink()).map_err(|e| e.to_string())?;
+        json.write_sheet(sheet, &range, opt)
+            .map_err(|e| e.to_string())?;
+        json.finish().map_err(|e| e.to_string())
+    })?;
+    timings.push(("json-workbook", json));
+    let jsonl = fastest(|| {
+        let mut jsonl =
+            json::JsonRowsWriter::new(std::io::sink(), false).map_err(|e| e.to_string())?;
+        jsonl
+            .write_sheet(sheet, &range, opt)
+            .map_err(|e| e.to_string())?;
+        jsonl.finish().map_err(|e| e.to_string())
+    })?;
+    timings.push(("jsonl", jsonl));
+    #[cfg(feature = "sqlite")]
+    {
+        let sqlite = fastest(|| {
+            let mut db =
+                crate::sqlite::SqliteWriter::open_in_memory().map_err(|e| e.to_string())?;
+            db.write_sheet(sheet, &range, opt)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        })?;
+        timings.push(("sqlite", sqlite));
+    }
+    #[cfg(feature = "parquet")]
+    {
+        let parquet = fastest(|| match crate::columnar::sheet_to_batch(&range, opt) {
+            Ok(Some(batch)) => crate::columnar::write_parquet_to(std::io::sink(), &batch)
+                .map_err(|e| e.to_string()),
+            // an empty sheet has nothing to write
+            Ok(None) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        })?;
+        timings.push(("parquet", parquet));
+    }
+    #[cfg(feature = "arrow")]
+    {
+        let arrow = fastest(|| match crate::columnar::sheet_to_batch(&range, opt) {
+            Ok(Some(batch)) => {
+                crate::columnar::write_arrow_to(std::io::sink(), &batch).map_err(|e| e.to_string())
+            }
+            Ok(None) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        })?;
+        timings.push(("arrow", arrow));
+    }
+    #[cfg(feature = "msgpack")]
+    {
+        let msgpack = fastest(|| {
+            crate::msgpack::write_sheet(std::io::sink(), &range, opt)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        })?;
+        timings.push(("msgpack", msgpack));
+    }
+
+    let base = timings
+        .iter()
+        .map(|(_, t)| *t)
+        .min()
+        .unwrap_or_default()
+        .max(Duration::from_nanos(1));
+    let ms = |t: Duration| format!("{:.3}", t.as_secs_f64() * 1000.0);
+    let mut rows = vec![vec!["(parse)".to_string(), ms(parse), String::new()]];
+    for (format, t) in timings {
+        rows.push(vec![
+            format.to_string(),
+            ms(t),
+            format!("{:.2}x", t.as_secs_f64() / base.as_secs_f64()),
+        ]);
+    }
+    eprintln!("sheet `{}`, fastest of {} runs:", sheet, RUNS);
+    print_table(&["format", "ms", "relative"], &rows, &[1, 2]);
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use calamine::{Data, Range};
+
+/// Parse a cell reference like `B2` into 0-based `(row, col)`, or a column like `B` into `(None, col)`.
+pub fn parse_ref(s: &str) -> Option<(Option<u32>, u32)> {
+    let split = s
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(s.len());
+    let (letters, digits) = s.split_at(split);
+    if letters.is_empty() || letters.len() > 3 {
+        return None;
+    }
+    let col = letters.chars().fold(0, |col, c| {
+        col * 26 + (c.to_ascii_uppercase() as u32 - 'A' as u32 + 1)
+    }) - 1;
+    if digits.is_empty() {
+        return Some((None, col));
+    }
+    match digits.parse::<u32>() {
+        Ok(row) if row > 0 => Some((Some(row - 1), col)),
+        _ => None,
+    }
+}
+
+/// Worksheet area of `--range`, like `A1:D100`, the whole columns `B:D` or the open-ended `B2:`.
+#[derive(Clone, Copy, Debug)]
+pub struct CellRange {
+    start: (u32, u32),
+    end: (u32, u32),
+}
+
+impl CellRange {
+    /// Intersection of the area with the used range from `start` to `end`, none if they are disjoint.
+    pub fn clip(&self, start: (u32, u32), end: (u32, u32)) -> Option<((u32, u32), (u32, u32))> {
+        let start = (self.start.0.max(start.0), self.start.1.max(start.1));
+        let end = (self.end.0.min(end.0), self.end.1.min(end.1));
+        if start.0 > end.0 || start.1 > end.1 {
+            return None;
+        }
+        Some((start, end))
+    }
+
+    /// Cells of a range within the area, kept at their worksheet positions.
+    pub fn restrict(&self, range: &Range<Data>) -> Range<Data> {
+        let clipped = match (range.start(), range.end()) {
+            (Some(start), Some(end)) => self.clip(start, end),
+            _ => None,
+        };
+        match clipped {
+            Some((start, end)) => range.range(start, end),
+            None => Range::empty(),
+        }
+    }
+}
+
+impl std::str::FromStr for CellRange {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid range `{}`, like `A1:D100`, `B:D` or `A1:` to the last row and column",
+                str
+            )
+        };
+        let (first, last) = match str.split_once(':') {
+            Some((first, last)) => (first, Some(last.trim())),
+            None => (str, None),
+        };
+        let first = parse_ref(first.trim()).ok_or_else(invalid)?;
+        let last = match last {
+            Some("") => (Some(u32::MAX), u32::MAX),
+            Some(last) => parse_ref(last).ok_or_else(invalid)?,
+            None => first,
+        };
+        // columns are from the first row, or to the last row
+        let start = (first.0.unwrap_or(0), first.1);
+        let end = (last.0.unwrap_or(u32::MAX), last.1);
+        if start.0 > end.0 || start.1 > end.1 {
+            return Err(format!("range `{}` is reversed", str));
+        }
+        Ok(CellRange { start, end })
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use calamine::{Data, Range};
+
+use crate::header::header_names;
+use crate::schema::{infer_types, ColumnType};
+use crate::{format_cell, Opt};
+
+fn data_type(column_type: ColumnType) -> DataType {
+    match column_type {
+        ColumnType::Boolean => DataType::Boolean,
+        ColumnType::Integer => DataType::Int64,
+        ColumnType::Real => DataType::Float64,
+        ColumnType::Text => DataType::Utf8,
+    }
+}
+
+/// Build the column of `col` in `rows`, empty and error cells are nulls.
+fn column(rows: &[&[Data]], col: usize, column_type: ColumnType, opt: &Opt) -> ArrayRef {
+    let cells = rows.iter().map(|row| row.get(col).unwrap_or(&Data::Empty));
+    match column_type {
+        ColumnType::Boolean => Arc::new(
+            cells
+                .map(|c| match c {
+                    Data::Bool(b) => Some(*b),
+                    _ => None,
+                })
+                .collect::<BooleanArray>(),
+        ),
+        ColumnType::Integer => Arc::new(
+            cells
+                .map(|c| match c {
+                    Data::Int(i) => Some(*i),
+                    Data::Float(f) => Some(*f as i64),
+                    Data::Bool(b) => Some(*b as i64),
+                    _ => None,
+                })
+                .collect::<Int64Array>(),
+        ),
+        ColumnType::Real => Arc::new(
+            cells
+                .map(|c| match c {
+                    Data::Float(f) => Some(*f),
+                    Data::Int(i) => Some(*i as f64),
+                    Data::Bool(b) => Some(*b as i64 as f64),
+                    _ => None,
+                })
+                .collect::<Float64Array>(),
+        ),
+        ColumnType::Text => Arc::new(
+            cells
+                .map(|c| match c {
+                    Data::Empty | Data::Error(_) => None,
+                    c => Some(format_cell(c, opt)),
+                })
+                .collect::<StringArray>(),
+        ),
+    }
+}
+
+/// Convert a sheet to a record batch, the first row is used as column names.
+///
+/// Returns `None` for an empty sheet which has no columns,
+/// or an error if a value does not match the type inferred from sampled rows (see `--infer-rows`).
+pub fn sheet_to_batch(range: &Range<Data>, opt: &Opt) -> Result<Option<RecordBatch>, ArrowError> {
+    let mut rows = range.rows();
+    let col0 = range.start().map_or(0, |(_, col)| col as usize);
+    let names = match rows.next() {
+        Some(row) => header_names(row, col0, opt),
+        None => return Ok(None),
+    };
+    let data: Vec<&[Data]> = rows.collect();
+    let types = infer_types(&data, names.len(), opt.infer_rows.map(NonZeroUsize::get));
+    // values after the sampled rows may not fit in the inferred types
+    for (i, row) in data.iter().enumerate() {
+        for (col, t) in types.iter().enumerate() {
+            match row.get(col) {
+                Some(cell) if !t.accepts(cell) => {
+                    return Err(ArrowError::CastError(format!(
+                        "value `{}` at row {} of column `{}` does not match the inferred type {}, try a larger --infer-rows",
+                        format_cell(cell, opt),
+                        i + 1,
+                        names[col],
+                        data_type(*t)
+                    )));
+                }
+                _ => {}
+            }
+        }
+    }
+    let fields: Vec<Field> = names
+        .iter()
+        .zip(&types)
+        .map(|(name, t)| Field::new(name, data_type(*t), true))
+        .collect();
+    let columns = types
+        .iter()
+        .enumerate()
+        .map(|(col, t)| column(&data, col, *t, opt))
+        .collect();
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).map(Some)
+}
+
+/// Write a record batch to a new Parquet file.
+#[cfg(feature = "parquet")]
+pub fn write_parquet(path: &Path, batch: &RecordBatch) -> parquet::errors::Result<()> {
+    write_parquet_to(File::create(path)?, batch)
+}
+
+/// Write a record batch in Parquet format to the writer.
+#[cfg(feature = "parquet")]
+pub fn write_parquet_to<W: Write + Send>(
+    writer: W,
+    batch: &RecordBatch,
+) -> parquet::errors::Result<()> {
+    let mut writer = parquet::arrow::ArrowWriter::try_new(writer, batch.schema(), None)?;
+    writer.write(batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Write a record batch to a new Arrow IPC file.
+#[cfg(feature = "arrow")]
+pub fn write_arrow(path: &Path, batch: &RecordBatch) -> Result<(), ArrowError> {
+    write_arrow_to(File::create(path)?, batch)
+}
+
+/// Write a record batch in Arrow IPC file format to the writer.
+#[cfg(feature = "arrow")]
+pub fn write_arrow_to<W: Write>(writer: W, batch: &RecordBatch) -> Result<(), ArrowError> {
+    let mut writer = arrow_ipc::writer::FileWriter::try_new(writer, &batch.schema())?;
+    writer.write(batch)?;
+    writer.finish()
+}
@@ -0,0 +1,306 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use structopt::StructOpt;
+
+use crate::output::{Encoded, RawLines};
+use crate::progress::Progress;
+use crate::workbook::Workbook;
+use crate::{
+    check_csv_options, command_line, create_output, csv_writer, file_extension, filter_sheets,
+    open_workbook, output_name, sheet_index, write_meta_header, write_sheet, write_sheet_file,
+    ByteLimit, Delimiter, Format, NameTemplate, Opt, SheetSelection, SheetSelector,
+};
+
+/// Converter of workbook sheets to csv, the same conversion as the command line.
+///
+/// Options are set builder-style, and the workbook is opened by the first call to read it:
+///
+/// ```no_run
+/// use xlsx2csv::{Converter, Delimiter, SheetSelector};
+///
+/// let mut converter = Converter::new("input.xlsx")?
+///     .delimiter(Delimiter(';'))
+///     .select(SheetSelector::ByRange(0, 2))
+///     .skip_rows(1);
+/// converter.convert_sheet_to_writer("Sheet1", std::io::stdout())?;
+/// let files = converter.convert_all("out".as_ref())?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// Other options of the command line are set by `from_args`. The conversion shares its code with the binary,
+/// and all its failures are returned as errors, the process is never exited.
+pub struct Converter {
+    opt: Opt,
+    workbook: Option<Workbook>,
+}
+
+impl Converter {
+    /// Converter of the workbook at `path` with the default options, `-` reads it from stdin.
+    ///
+    /// It's `from_args` without other arguments, errors are the messages of argument parsing.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, String> {
+        Self::from_args(path, Vec::<OsString>::new())
+    }
+
+    /// Converter of the workbook at `path` with command line options, like `["--skip-empty-rows"]`.
+    ///
+    /// Only options of csv output apply, errors are the messages of invalid options.
+    pub fn from_args<I, T>(path: impl AsRef<Path>, args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let args: Vec<OsString> = std::iter::once(OsString::from("xlsx2csv"))
+            .chain(args.into_iter().map(Into::into))
+            .chain([OsString::from("--"), path.as_ref().into()])
+            .collect();
+        let mut opt = Opt::from_iter_safe(&args).map_err(|e| e.message)?;
+        opt.args = command_line(&args[1..]);
+        match opt.format {
+            Format::Csv => {}
+            Format::Tsv => opt.delimiter = Delimiter('\t'),
+            _ => return Err("the converter writes csv, use `--format csv` or `tsv`".to_string()),
+        }
+        let lists = std::mem::take(&mut opt.select_lists);
+        if lists.iter().any(SheetSelection::is_stdin) {
+            return Err("`--select -` is not supported by the converter".to_string());
+        }
+        opt.select = SheetSelection::concat(lists);
+        Ok(Converter {
+            opt,
+            workbook: None,
+        })
+    }
+
+    /// Delimiter of csv fields, an ASCII one. The extension of `convert_all` files is `tsv` for tabs.
+    pub fn delimiter(mut self, delimiter: Delimiter) -> Self {
+        self.opt.delimiter = delimiter;
+        self
+    }
+
+    /// Add a selector of sheets for `convert_all`, all sheets are converted if there's none.
+    pub fn select(mut self, selector: SheetSelector) -> Self {
+        let selection = SheetSelection {
+            lists: vec![(String::new(), vec![selector])],
+        };
+        let selections = self.opt.select.take().into_iter().chain([selection]);
+        self.opt.select = SheetSelection::concat(selections.collect());
+        self
+    }
+
+    /// Drop the first N rows of sheets, like `--skip-rows`.
+    pub fn skip_rows(mut self, rows: usize) -> Self {
+        self.opt.skip_rows = rows;
+        self
+    }
+
+    /// Drop the first N columns of sheets, like `--skip-cols`.
+    pub fn skip_cols(mut self, cols: usize) -> Self {
+        self.opt.skip_cols = cols;
+        self
+    }
+
+    /// Skip rows without any non-empty cell, like `--skip-empty-rows`.
+    pub fn skip_empty_rows(mut self, skip: bool) -> Self {
+        self.opt.skip_empty_rows = skip;
+        self
+    }
+
+    /// Swap rows for columns of sheets as they are read, like `--transpose`.
+    pub fn transpose(mut self, transpose: bool) -> Self {
+        self.opt.transpose = transpose;
+        // sheets are transposed by the workbook
+        self.workbook = None;
+        self
+    }
+
+    /// Names of all sheets in the workbook order.
+    pub fn sheet_names(&mut self) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(self.workbook()?.sheet_names())
+    }
+
+    /// Write a sheet of the name as csv to `w`, returns the number of rows written.
+    pub fn convert_sheet_to_writer(
+        &mut self,
+        sheet: &str,
+        w: impl Write,
+    ) -> Result<usize, Box<dyn Error>> {
+        check_csv_options(&self.opt)?;
+        self.parse_sheet(sheet)?;
+        let opt = &self.opt;
+        let workbook = self.workbook.as_mut().expect("opened workbook");
+        let mut wtr =
+            csv_writer(opt)?.from_writer(RawLines::new(Encoded::new(w, opt.encoding, opt.bom)?));
+        write_meta_header(&mut wtr, workbook, sheet, opt)?;
+        let rows = write_sheet(workbook, sheet, &mut wtr, opt, None, &Progress::disabled())?;
+        wtr.flush()?;
+        Ok(rows)
+    }
+
+    /// Write the selected (or all) sheets to csv files in `out_dir` as `-u` does, returns their paths.
+    ///
+    /// Files are named by `--name-template` if set, existing files are overwritten.
+    pub fn convert_all(&mut self, out_dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        check_csv_options(&self.opt)?;
+        let sheetnames = self.sheet_names()?;
+        let sheets: Vec<String> = match &self.opt.select {
+            Some(select) => select.find_in(&sheetnames, self.opt.name_match())?,
+            None => filter_sheets(&sheetnames, &self.opt)?,
+        }
+        .into_iter()
+        .cloned()
+        .collect();
+        let template = NameTemplate::new(&self.opt)?;
+        let ext = file_extension(&self.opt);
+        let limit = ByteLimit::new(None);
+        let mut used = HashSet::new();
+        let mut paths = Vec::new();
+        for sheet in &sheets {
+            let index = sheet_index(&sheetnames, sheet);
+            let name = output_name(&sheetnames, sheet);
+            let path = match template.render(sheet, &name, index, &ext, &mut used) {
+                Some(filename) => out_dir.join(filename),
+                // not matching `--name-from-regex` with `--require-name-match`
+                None => continue,
+            };
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("create directory {}: {}", parent.display(), e))?;
+            }
+            self.parse_sheet(sheet)?;
+            let workbook = self.workbook.as_mut().expect("opened workbook");
+            let file = create_output(&path)?;
+            write_sheet_file(
+                workbook,
+                sheet,
+                file,
+                &path,
+                &self.opt,
+                &limit,
+                &Progress::disabled(),
+            )
+            .map_err(|e| format!("write sheet `{}` to {}: {}", sheet, path.display(), e))?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    fn workbook(&mut self) -> Result<&mut Workbook, String> {
+        if self.workbook.is_none() {
+            self.workbook = Some(open_workbook(&self.opt)?);
+        }
+        Ok(self.workbook.as_mut().expect("opened workbook"))
+    }
+
+    /// Parse a sheet of the name into the workbook cache before writing it.
+    ///
+    /// Parse errors are handled by `--on-parse-error`, an error is returned with `abort`.
+    fn parse_sheet(&mut self, sheet: &str) -> Result<(), String> {
+        let workbook = self.workbook()?;
+        if !workbook.sheet_names().iter().any(|s| s == sheet) {
+            return Err(format!("sheet name `{}` is not in the workbook", sheet));
+        }
+        // the error already names the sheet
+        workbook.range(sheet).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST: &str = "examples/test.xlsx";
+    /// Workbook of a sheet `Good`, and a sheet `Data` with a cell of an unknown type in its last row.
+    const DAMAGED: &str = "tests/data/damaged.xlsx";
+
+    fn csv(converter: &mut Converter, sheet: &str) -> Result<String, Box<dyn Error>> {
+        let mut out = Vec::new();
+        converter.convert_sheet_to_writer(sheet, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn sheet_is_converted_to_a_writer_with_the_options() {
+        let mut converter = Converter::new(TEST).unwrap();
+        let mut out = Vec::new();
+        assert_eq!(
+            converter.convert_sheet_to_writer("ages", &mut out).unwrap(),
+            3
+        );
+        assert_eq!(out, b"name,age\na,13\nb,14\n");
+        let mut converter = Converter::new(TEST)
+            .unwrap()
+            .delimiter(Delimiter(';'))
+            .skip_rows(1);
+        let mut out = Vec::new();
+        assert_eq!(
+            converter.convert_sheet_to_writer("data", &mut out).unwrap(),
+            2
+        );
+        assert_eq!(out, b"0x1;A1;2020-09-14\n0x2;A2;2020-09-14\n");
+        let err = converter
+            .convert_sheet_to_writer("nope", Vec::new())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "sheet name `nope` is not in the workbook");
+    }
+
+    #[test]
+    fn selected_sheets_are_converted_to_files() {
+        let dir = std::env::temp_dir().join("xlsx2csv_converter_convert_all");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut converter = Converter::new(TEST).unwrap();
+        let paths = converter.convert_all(&dir).unwrap();
+        assert_eq!(paths, [dir.join("ages.csv"), dir.join("data.csv")]);
+        let ages = std::fs::read_to_string(&paths[0]).unwrap();
+        assert_eq!(ages, "name,age\na,13\nb,14\n");
+        let mut converter = Converter::from_args(TEST, ["--format", "tsv"])
+            .unwrap()
+            .select(SheetSelector::ByName("data".to_string()));
+        let paths = converter.convert_all(&dir).unwrap();
+        assert_eq!(paths, [dir.join("data.tsv")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn options_of_other_outputs_are_rejected() {
+        let err = |args: &[&str]| Converter::from_args(TEST, args).err().unwrap();
+        assert!(err(&["--format", "json"]).contains("the converter writes csv"));
+        assert!(err(&["--select", "-"]).contains("`--select -` is not supported"));
+        assert!(err(&["--no-such-option"]).contains("--no-such-option"));
+    }
+
+    #[test]
+    fn parse_errors_are_handled_by_on_parse_error() {
+        let err = csv(&mut Converter::new(DAMAGED).unwrap(), "Data").unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("parse sheet `Data`: Xlsx error"),
+            "{}",
+            err
+        );
+        let args = ["--on-parse-error", "skip-sheet"];
+        let mut skip = Converter::from_args(DAMAGED, args).unwrap();
+        assert_eq!(csv(&mut skip, "Data").unwrap(), "");
+        assert_eq!(csv(&mut skip, "Good").unwrap(), "id\n1\n");
+        let args = ["--on-parse-error", "emit-partial"];
+        let mut partial = Converter::from_args(DAMAGED, args).unwrap();
+        assert_eq!(csv(&mut partial, "Data").unwrap(), "id,name\n1,a\n2,b\n");
+    }
+
+    #[test]
+    fn meta_header_has_the_arguments_of_the_converter() {
+        let args = ["--with-meta-header"];
+        let mut converter = Converter::from_args("examples/test.xlsx", args).unwrap();
+        let csv = csv(&mut converter, "ages").unwrap();
+        assert!(
+            csv.contains("\n# args: --with-meta-header -- examples/test.xlsx\nname,age\n"),
+            "{}",
+            csv
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+
+use calamine::{Data, Range};
+
+use crate::header::{dedup_names, strip_prefix};
+use crate::schema::{infer_types, ColumnType};
+use crate::{data_rows, data_start, format_cell, Opt};
+
+/// SQL dialect of `--emit-ddl` statements.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dialect {
+    Postgres,
+    Mysql,
+    Sqlite,
+}
+
+impl std::str::FromStr for Dialect {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "postgres" | "postgresql" => Ok(Dialect::Postgres),
+            "mysql" => Ok(Dialect::Mysql),
+            "sqlite" => Ok(Dialect::Sqlite),
+            s => Err(format!(
+                "unknown dialect `{}`, supports: postgres, mysql, sqlite",
+                s
+            )),
+        }
+    }
+}
+
+impl Dialect {
+    pub fn column_type(self, column_type: ColumnType) -> &'static str {
+        use ColumnType::*;
+        match (self, column_type) {
+            (Dialect::Sqlite, Boolean | Integer) => "INTEGER",
+            (Dialect::Sqlite, Real) => "REAL",
+            (_, Boolean) => "BOOLEAN",
+            (_, Integer) => "BIGINT",
+            (Dialect::Mysql, Real) => "DOUBLE",
+            (_, Real) => "DOUBLE PRECISION",
+            (_, Text) => "TEXT",
+        }
+    }
+
+    /// Quote an identifier, `"name"` or `` `name` `` for mysql.
+    pub fn quote(self, ident: &str) -> String {
+        match self {
+            Dialect::Mysql => format!("`{}`", ident.replace('`', "``")),
+            _ => format!("\"{}\"", ident.replace('"', "\"\"")),
+        }
+    }
+}
+
+/// Make a valid SQL identifier: non-alphanumeric characters are replaced with `_`.
+pub fn sanitize_identifier(name: &str, fallback: &str) -> String {
+    let mut ident: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    if ident.is_empty() {
+        ident = fallback.to_string();
+    } else if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+/// Writer of `CREATE TABLE` statements for sheets, tables are named by the sanitized sheet names.
+pub struct DdlWriter<W: Write> {
+    inner: W,
+    dialect: Dialect,
+    tables: HashSet<String>,
+}
+
+impl<W: Write> DdlWriter<W> {
+    pub fn new(inner: W, dialect: Dialect) -> Self {
+        DdlWriter {
+            inner,
+            dialect,
+            tables: HashSet::new(),
+        }
+    }
+
+    /// Write the statement of a sheet, columns are named by the first row and typed by the rows below.
+    pub fn write_sheet(&mut self, sheet: &str, range: &Range<Data>, opt: &Opt) -> io::Result<()> {
+        let table =
+            dedup_names(vec![sanitize_identifier(sheet, "sheet")], &mut self.tables).remove(0);
+        let mut rows = data_rows(range, opt);
+        let naming = opt.empty_header_name.clone().unwrap_or_default();
+        let col0 = data_start(range, opt).1 as usize;
+        let header: Vec<String> = match rows.next() {
+            Some(row) => row
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    let name = format_cell(c, opt);
+                    sanitize_identifier(strip_prefix(&name, opt), &naming.render(i, col0 + i))
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        if header.is_empty() {
+            return writeln!(self.inner, "-- sheet `{}` is empty, no table", sheet);
+        }
+        let columns = dedup_names(header, &mut HashSet::new());
+        let data: Vec<&[Data]> = rows.collect();
+        let types = infer_types(&data, columns.len(), opt.infer_rows.map(NonZeroUsize::get));
+        writeln!(self.inner, "CREATE TABLE {} (", self.dialect.quote(&table))?;
+        for (i, (name, t)) in columns.iter().zip(&types).enumerate() {
+            let sep = if i + 1 < columns.len() { "," } else { "" };
+            writeln!(
+                self.inner,
+                "    {} {}{}",
+                self.dialect.quote(name),
+                self.dialect.column_type(*t),
+                sep
+            )?;
+        }
+        writeln!(self.inner, ");")
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
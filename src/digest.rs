@@ -0,0 +1,27 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::io::{self, Write};
+
+/// Writer that hashes all bytes written instead of keeping them, to compare outputs cheaply.
+#[derive(Default)]
+pub struct HashWriter {
+    hasher: DefaultHasher,
+}
+
+impl HashWriter {
+    /// Hash of all bytes written so far.
+    pub fn finish(&self) -> u64 {
+        self.hasher.finish()
+    }
+}
+
+impl Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hasher.write(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
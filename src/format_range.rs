@@ -0,0 +1,125 @@
+use calamine::{Data, DataType, ExcelDateTime, ExcelDateTimeType};
+use chrono::format::{Item, StrftimeItems};
+
+use crate::cell_range::parse_ref;
+use crate::{format_cell, in_date_system, DateSystem, Opt};
+
+/// Rendering of cells in a `--format-range`.
+#[derive(Clone, Debug)]
+pub enum CellSpec {
+    /// Numbers and dates as dates (or date times) of a strftime pattern.
+    Date(String),
+    /// The raw value as text, dates as their serial numbers.
+    Text,
+    /// Numbers with a fixed count of decimals.
+    Number(usize),
+}
+
+impl std::str::FromStr for CellSpec {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let (kind, arg) = match str.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (str, None),
+        };
+        match (kind, arg) {
+            ("date", None) => Ok(CellSpec::Date("%Y-%m-%d".to_string())),
+            ("date", Some(pattern)) => {
+                if StrftimeItems::new(pattern).any(|item| item == Item::Error) {
+                    return Err(format!("invalid date format `{}`", pattern));
+                }
+                Ok(CellSpec::Date(pattern.to_string()))
+            }
+            ("text", None) => Ok(CellSpec::Text),
+            ("number", None) => Ok(CellSpec::Number(0)),
+            ("number", Some(decimals)) => decimals
+                .parse()
+                .map(CellSpec::Number)
+                .map_err(|_| format!("invalid number of decimals `{}`", decimals)),
+            _ => Err(format!(
+                "unknown cell format `{}`, supports: date[:PATTERN], text, number[:DECIMALS]",
+                str
+            )),
+        }
+    }
+}
+
+/// Cell format override of a worksheet range, like `B2:B100=date:%Y-%m-%d`.
+#[derive(Clone, Debug)]
+pub struct FormatRange {
+    start: (u32, u32),
+    end: (u32, u32),
+    spec: CellSpec,
+}
+
+impl FormatRange {
+    /// Whether the 0-based worksheet position is in the range.
+    pub fn contains(&self, row: u32, col: u32) -> bool {
+        (self.start.0..=self.end.0).contains(&row) && (self.start.1..=self.end.1).contains(&col)
+    }
+
+    pub fn render(&self, cell: &Data, opt: &Opt) -> String {
+        match (&self.spec, cell) {
+            (CellSpec::Date(pattern), Data::DateTime(dt)) => format_date(dt, pattern, opt),
+            (CellSpec::Date(pattern), Data::Int(_) | Data::Float(_)) => {
+                let serial = cell.as_f64().unwrap_or_default();
+                let is_1904 = opt.date_system == DateSystem::Excel1904;
+                let dt = ExcelDateTime::new(serial, ExcelDateTimeType::DateTime, is_1904);
+                format_date(&dt, pattern, opt)
+            }
+            (CellSpec::Text, Data::DateTime(dt)) => format!("{}", dt.as_f64()),
+            (CellSpec::Number(decimals), Data::Int(_) | Data::Float(_) | Data::Bool(_)) => {
+                let value = match cell {
+                    Data::Bool(b) => *b as i64 as f64,
+                    cell => cell.as_f64().unwrap_or_default(),
+                };
+                format!("{:.*}", decimals, value)
+            }
+            (CellSpec::Number(decimals), Data::DateTime(dt)) => {
+                format!("{:.*}", decimals, dt.as_f64())
+            }
+            (CellSpec::Number(decimals), Data::String(s)) => match s.trim().parse::<f64>() {
+                Ok(value) => format!("{:.*}", decimals, value),
+                Err(_) => format_cell(cell, opt),
+            },
+            (_, cell) => format_cell(cell, opt),
+        }
+    }
+}
+
+fn format_date(datetime: &ExcelDateTime, pattern: &str, opt: &Opt) -> String {
+    let datetime = in_date_system(datetime, opt.date_system);
+    match datetime.as_datetime() {
+        Some(dt) => dt.format(pattern).to_string(),
+        None => format!("{}", datetime.as_f64()),
+    }
+}
+
+impl std::str::FromStr for FormatRange {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let (range, spec) = str
+            .split_once('=')
+            .ok_or_else(|| format!("expect RANGE=FORMAT, like `B2:B100=text`, got `{}`", str))?;
+        let invalid = || format!("invalid range `{}`, like `B2`, `B2:D100` or `B:D`", range);
+        let (first, last) = range.split_once(':').unwrap_or((range, range));
+        let (first, last) = match (parse_ref(first.trim()), parse_ref(last.trim())) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err(invalid()),
+        };
+        // whole columns like `B:D`
+        let (start, end) = match (first, last) {
+            ((Some(r0), c0), (Some(r1), c1)) => ((r0, c0), (r1, c1)),
+            ((None, c0), (None, c1)) => ((0, c0), (u32::MAX, c1)),
+            _ => return Err(invalid()),
+        };
+        if start.0 > end.0 || start.1 > end.1 {
+            return Err(format!("range `{}` is reversed", range));
+        }
+        Ok(FormatRange {
+            start,
+            end,
+            spec: spec.parse()?,
+        })
+    }
+}
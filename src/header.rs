@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+
+use calamine::Data;
+
+use crate::{format_cell, Opt};
+
+/// Make names unique by appending `_2`, `_3`... to repeated ones (case-insensitively).
+pub fn dedup_names(names: Vec<String>, seen: &mut HashSet<String>) -> Vec<String> {
+    names
+        .into_iter()
+        .map(|name| {
+            let mut unique = name.clone();
+            let mut n = 1;
+            while !seen.insert(unique.to_lowercase()) {
+                n += 1;
+                unique = format!("{}_{}", name, n);
+            }
+            unique
+        })
+        .collect()
+}
+
+/// Strip the `--strip-header-prefix` from a header name, names without it are unchanged.
+pub fn strip_prefix<'a>(name: &'a str, opt: &Opt) -> &'a str {
+    match &opt.strip_header_prefix {
+        Some(prefix) => name.strip_prefix(prefix.as_str()).unwrap_or(name),
+        None => name,
+    }
+}
+
+/// Column names from the header row starting at worksheet column `col0`, for keyed outputs.
+///
+/// Names are stripped of `--strip-header-prefix` and trimmed, empty ones are named by `--empty-header-name`
+/// (`column_{index}` by default) and repeated ones are made unique.
+pub fn header_names(row: &[Data], col0: usize, opt: &Opt) -> Vec<String> {
+    let naming = opt.empty_header_name.clone().unwrap_or_default();
+    let names = row
+        .iter()
+        .enumerate()
+        .map(
+            |(i, c)| match strip_prefix(&format_cell(c, opt), opt).trim() {
+                "" => naming.render(i, col0 + i),
+                name => name.to_string(),
+            },
+        )
+        .collect();
+    dedup_names(names, &mut HashSet::new())
+}
+
+/// Output columns by header names, one per line of a `--column-order-file`.
+#[derive(Clone, Debug)]
+pub struct ColumnOrder {
+    names: Vec<String>,
+}
+
+impl ColumnOrder {
+    /// Map the listed names to columns of the header row, `None` for missing ones.
+    ///
+    /// Names are compared trimmed, a repeated name takes the next column of the name.
+    /// Columns not listed are appended in order, unless `drop_extra`.
+    /// With `strict`, missing names or extra columns (not dropped) are an error.
+    pub fn mapping(
+        &self,
+        header: &[String],
+        drop_extra: bool,
+        strict: bool,
+    ) -> Result<Vec<Option<usize>>, String> {
+        let mut used = vec![false; header.len()];
+        let mut mapping = Vec::with_capacity(self.names.len());
+        let mut missing = Vec::new();
+        for name in &self.names {
+            let col = (0..header.len()).find(|&j| !used[j] && header[j].trim() == name);
+            match col {
+                Some(j) => used[j] = true,
+                None => missing.push(name.as_str()),
+            }
+            mapping.push(col);
+        }
+        let extra: Vec<usize> = (0..header.len()).filter(|&j| !used[j]).collect();
+        if strict && !missing.is_empty() {
+            return Err(format!(
+                "missing columns of the schema: {}",
+                missing.join(", ")
+            ));
+        }
+        if strict && !drop_extra && !extra.is_empty() {
+            let names: Vec<&str> = extra.iter().map(|&j| header[j].as_str()).collect();
+            return Err(format!("columns not in the schema: {}", names.join(", ")));
+        }
+        if !drop_extra {
+            mapping.extend(extra.into_iter().map(Some));
+        }
+        Ok(mapping)
+    }
+
+    /// Listed names missing in the header row of the mapping.
+    pub fn missing(&self, mapping: &[Option<usize>]) -> Vec<&str> {
+        self.names
+            .iter()
+            .zip(mapping)
+            .filter(|(_, col)| col.is_none())
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Parse a comma-separated list of names, like `Name,Email` of `--columns`.
+    pub fn from_list(list: &str) -> Result<Self, String> {
+        let names: Vec<String> = list
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .collect();
+        if names.iter().any(String::is_empty) {
+            return Err(format!("empty column name in `{}`", list));
+        }
+        Ok(ColumnOrder { names })
+    }
+
+    /// Reorder a row by the mapping, missing columns are filled by `fill`.
+    pub fn apply(&self, row: &[String], mapping: &[Option<usize>], fill: &str) -> Vec<String> {
+        mapping
+            .iter()
+            .map(|col| col.and_then(|j| row.get(j)).map_or(fill, String::as_str))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Header row reordered by the mapping, missing columns are named by the schema.
+    pub fn apply_header(&self, header: &[String], mapping: &[Option<usize>]) -> Vec<String> {
+        mapping
+            .iter()
+            .enumerate()
+            .map(|(i, col)| match col {
+                Some(j) => header[*j].clone(),
+                None => self.names[i].clone(),
+            })
+            .collect()
+    }
+}
+
+impl std::str::FromStr for ColumnOrder {
+    type Err = String;
+    /// Read the names from the file path, blank lines are ignored.
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("read {}: {}", path, e))?;
+        let names: Vec<String> = text
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        if names.is_empty() {
+            return Err(format!("no column names in {}", path));
+        }
+        Ok(ColumnOrder { names })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structopt::StructOpt;
+
+    fn names(row: &[&str], args: &[&str]) -> Vec<String> {
+        let opt = Opt::from_iter(["xlsx2csv", "input.xlsx"].iter().chain(args));
+        let row: Vec<Data> = row.iter().map(|s| Data::String(s.to_string())).collect();
+        header_names(&row, 0, &opt)
+    }
+
+    #[test]
+    fn header_names_are_stripped_of_the_prefix() {
+        let row = ["tbl_id", "name", "tbl_", "tbl_tbl_x"];
+        assert_eq!(
+            names(&row, &["--strip-header-prefix", "tbl_"]),
+            ["id", "name", "column_3", "tbl_x"]
+        );
+        assert_eq!(names(&row, &[]), row);
+    }
+
+    #[test]
+    fn stripped_names_are_made_unique() {
+        let row = ["tbl_id", "id", " "];
+        assert_eq!(
+            names(&row, &["--strip-header-prefix", "tbl_"]),
+            ["id", "id_2", "column_3"]
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use std::io::{self, Write};
+
+use calamine::{Data, Range};
+
+use crate::writer::SheetWriter;
+use crate::{data_rows, render_cell, render_header_cell, Opt};
+
+const HEADER: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<style>
+table { border-collapse: collapse; margin-bottom: 2em; font-family: sans-serif; font-size: 14px; }
+caption { font-weight: bold; text-align: left; padding: 4px 0; }
+td { border: 1px solid #ccc; padding: 2px 6px; white-space: pre-wrap; }
+tr:nth-child(even) { background: #f6f6f6; }
+</style>
+</head>
+<body>
+"#;
+
+const FOOTER: &str = "</body>\n</html>\n";
+
+/// Escape text for HTML content and attributes.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// HTML preview document with one table per sheet.
+pub struct HtmlWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> HtmlWriter<W> {
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        inner.write_all(HEADER.as_bytes())?;
+        Ok(HtmlWriter { inner })
+    }
+}
+
+impl<W: Write> SheetWriter for HtmlWriter<W> {
+    /// Write a sheet as a table captioned by the sheet name, returns the number of rows written.
+    fn write_sheet(&mut self, sheet: &str, range: &Range<Data>, opt: &Opt) -> io::Result<usize> {
+        writeln!(self.inner, "<table>")?;
+        writeln!(self.inner, "<caption>{}</caption>", escape(sheet))?;
+        let mut rows = 0;
+        for row in data_rows(range, opt) {
+            write!(self.inner, "<tr>")?;
+            for (col, cell) in row.iter().enumerate() {
+                // the first row is the header
+                let value = match rows {
+                    0 => render_header_cell(col, cell, opt)?,
+                    _ => render_cell(col, cell, opt)?,
+                };
+                write!(self.inner, "<td>{}</td>", escape(&value))?;
+            }
+            writeln!(self.inner, "</tr>")?;
+            rows += 1;
+        }
+        writeln!(self.inner, "</table>")?;
+        Ok(rows)
+    }
+
+    /// Close the document.
+    fn finish(mut self) -> io::Result<()> {
+        self.inner.write_all(FOOTER.as_bytes())?;
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,213 @@
+use std::io::{self, Write};
+
+use calamine::{Data, Range};
+use serde_json::{Map, Number, Value};
+
+use crate::header::header_names;
+use crate::writer::SheetWriter;
+use crate::{data_rows, data_start, format_cell, render_cell, Opt};
+
+/// JSON value of a cell, numbers and booleans are kept, empty and error cells are nulls.
+///
+/// Columns mapped by `--map-values` are strings of the mapped values.
+pub fn to_json(col: usize, cell: &Data, opt: &Opt) -> io::Result<Value> {
+    if opt.map_values.iter().any(|m| m.column == col) {
+        return Ok(Value::String(render_cell(col, cell, opt)?));
+    }
+    Ok(typed_json(cell, opt))
+}
+
+/// JSON value of a cell as `to_json` without `--map-values`, for header cells.
+pub fn typed_json(cell: &Data, opt: &Opt) -> Value {
+    match cell {
+        Data::Empty | Data::Error(_) => Value::Null,
+        Data::Int(i) => Value::from(*i),
+        // integral floats are written as integers, as in CSV
+        Data::Float(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => Value::from(*f as i64),
+        Data::Float(f) => Number::from_f64(*f).map_or(Value::Null, Value::Number),
+        Data::Bool(b) => Value::Bool(*b),
+        cell => Value::String(format_cell(cell, opt)),
+    }
+}
+
+/// Data rows of a sheet as objects keyed by the header (the first row), missing cells are nulls.
+fn row_objects<'a>(
+    range: &'a Range<Data>,
+    opt: &'a Opt,
+) -> impl Iterator<Item = io::Result<Map<String, Value>>> + 'a {
+    let col0 = data_start(range, opt).1 as usize;
+    let mut rows = data_rows(range, opt);
+    let keys = match rows.next() {
+        Some(row) => header_names(row, col0, opt),
+        None => Vec::new(),
+    };
+    rows.map(move |row| {
+        keys.iter()
+            .enumerate()
+            .map(|(col, key)| {
+                let value = match row.get(col) {
+                    Some(c) => to_json(col, c, opt)?,
+                    None => Value::Null,
+                };
+                Ok((key.clone(), value))
+            })
+            .collect()
+    })
+}
+
+/// Write a JSON value, across multiple lines with `--pretty`.
+fn write_value<W: Write>(inner: &mut W, value: &Map<String, Value>, opt: &Opt) -> io::Result<()> {
+    if opt.pretty {
+        serde_json::to_writer_pretty(inner, value)?;
+    } else {
+        serde_json::to_writer(inner, value)?;
+    }
+    Ok(())
+}
+
+/// Whole workbook as a JSON object of sheets, each is an array of row objects keyed by the header.
+pub struct JsonWorkbookWriter<W: Write> {
+    inner: W,
+    sheets: usize,
+}
+
+impl<W: Write> JsonWorkbookWriter<W> {
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        inner.write_all(b"{")?;
+        Ok(JsonWorkbookWriter { inner, sheets: 0 })
+    }
+}
+
+impl<W: Write> SheetWriter for JsonWorkbookWriter<W> {
+    /// Write a sheet as the array of its data rows, returns the number of rows written.
+    ///
+    /// The first row is used as keys, an empty sheet is an empty array.
+    fn write_sheet(&mut self, sheet: &str, range: &Range<Data>, opt: &Opt) -> io::Result<usize> {
+        self.inner
+            .write_all(if self.sheets == 0 { b"\n" } else { b",\n" })?;
+        self.sheets += 1;
+        serde_json::to_writer(&mut self.inner, sheet)?;
+        self.inner.write_all(b": [")?;
+        let mut written = 0;
+        for object in row_objects(range, opt) {
+            let object = object?;
+            self.inner
+                .write_all(if written == 0 { b"\n" } else { b",\n" })?;
+            write_value(&mut self.inner, &object, opt)?;
+            written += 1;
+        }
+        self.inner
+            .write_all(if written == 0 { b"]" } else { b"\n]" })?;
+        Ok(written)
+    }
+
+    /// Close the object.
+    fn finish(mut self) -> io::Result<()> {
+        self.inner
+            .write_all(if self.sheets == 0 { b"}\n" } else { b"\n}\n" })?;
+        self.inner.flush()
+    }
+}
+
+/// Data rows of sheets as objects keyed by the header of each sheet,
+/// one per line (JSON lines) or in a single JSON array of all sheets.
+pub struct JsonRowsWriter<W: Write> {
+    inner: W,
+    array: bool,
+    rows: usize,
+}
+
+impl<W: Write> JsonRowsWriter<W> {
+    /// Rows as JSON lines, or a JSON array if `array`.
+    pub fn new(mut inner: W, array: bool) -> io::Result<Self> {
+        if array {
+            inner.write_all(b"[")?;
+        }
+        Ok(JsonRowsWriter {
+            inner,
+            array,
+            rows: 0,
+        })
+    }
+}
+
+impl<W: Write> SheetWriter for JsonRowsWriter<W> {
+    /// Write the data rows of a sheet, `--pretty` only applies to arrays, as lines must be compact.
+    fn write_sheet(&mut self, _sheet: &str, range: &Range<Data>, opt: &Opt) -> io::Result<usize> {
+        let mut written = 0;
+        for object in row_objects(range, opt) {
+            let object = object?;
+            if self.array {
+                self.inner
+                    .write_all(if self.rows == 0 { b"\n" } else { b",\n" })?;
+                write_value(&mut self.inner, &object, opt)?;
+            } else {
+                serde_json::to_writer(&mut self.inner, &object)?;
+                self.inner.write_all(b"\n")?;
+            }
+            self.rows += 1;
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Close the array.
+    fn finish(mut self) -> io::Result<()> {
+        if self.array {
+            self.inner
+                .write_all(if self.rows == 0 { b"]\n" } else { b"\n]\n" })?;
+        }
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structopt::StructOpt;
+
+    fn range() -> Range<Data> {
+        let mut range = Range::new((0, 0), (1, 1));
+        range.set_value((0, 0), Data::String("id".to_string()));
+        range.set_value((0, 1), Data::String("name".to_string()));
+        range.set_value((1, 0), Data::Float(1.0));
+        range.set_value((1, 1), Data::String("a".to_string()));
+        range
+    }
+
+    fn workbook(args: &[&str]) -> String {
+        let opt = Opt::from_iter(["xlsx2csv", "input.xlsx"].iter().chain(args));
+        let mut out = Vec::new();
+        let mut writer = JsonWorkbookWriter::new(&mut out).unwrap();
+        assert_eq!(writer.write_sheet("Data", &range(), &opt).unwrap(), 1);
+        writer.finish().unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn pretty_spreads_row_objects_across_lines() {
+        let compact = workbook(&[]);
+        assert_eq!(compact, "{\n\"Data\": [\n{\"id\":1,\"name\":\"a\"}\n]\n}\n");
+        let pretty = workbook(&["--pretty"]);
+        assert!(
+            pretty.contains("{\n  \"id\": 1,\n  \"name\": \"a\"\n}"),
+            "{}",
+            pretty
+        );
+        let parse = |s: &str| serde_json::from_str::<Value>(s).unwrap();
+        assert_eq!(parse(&pretty), parse(&compact));
+    }
+
+    #[test]
+    fn pretty_keeps_json_lines_compact() {
+        let opt = Opt::from_iter(["xlsx2csv", "input.xlsx", "--pretty"]);
+        let mut out = Vec::new();
+        let mut writer = JsonRowsWriter::new(&mut out, false).unwrap();
+        writer.write_sheet("Data", &range(), &opt).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"id\":1,\"name\":\"a\"}\n"
+        );
+    }
+}
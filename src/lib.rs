@@ -0,0 +1,4287 @@
+//! Excel-like sheets to CSV converter.
+//!
+//! The `xlsx2csv` binary is a thin wrapper of `run`, and `Converter` converts sheets from
+//! other Rust programs with the same options.
+
+use calamine::{Data, ExcelDateTime, ExcelDateTimeType, Range};
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::File;
+use std::io::IsTerminal;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use structopt::StructOpt;
+
+use regex::{Regex, RegexBuilder};
+
+use limit::{ByteLimit, LimitedWriter};
+use output::{Encoded, OutputEncoding, OutputFile, RawLines};
+use progress::Progress;
+use summary::{Status, Summary};
+use workbook::{InputFormat, Workbook};
+use writer::SheetWriter;
+
+pub use converter::Converter;
+
+mod bench;
+mod cell_range;
+#[cfg(any(feature = "parquet", feature = "arrow"))]
+mod columnar;
+mod converter;
+mod ddl;
+mod digest;
+mod format_range;
+mod header;
+mod html;
+mod json;
+mod limit;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+mod output;
+mod progress;
+mod properties;
+mod schema;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod summary;
+mod tee;
+mod workbook;
+mod writer;
+
+/// How selectors match sheet names, see `--ignore-case` and `--first-match`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NameMatch {
+    pub ignore_case: bool,
+    pub first_match: bool,
+}
+
+/// Select sheet by id or by name, or sheets by an inclusive id range like `0-3`.
+#[derive(Clone, Debug)]
+pub enum SheetSelector {
+    ById(usize),
+    ByName(String),
+    ByRange(usize, usize),
+}
+
+impl SheetSelector {
+    /// Find all sheets selected, in ascending order for id ranges.
+    pub fn find_all_in<'a>(
+        &self,
+        sheetnames: &'a [String],
+        matching: NameMatch,
+    ) -> Result<Vec<&'a String>, String> {
+        match self {
+            SheetSelector::ByRange(start, end) => {
+                if start > end {
+                    Err(format!(
+                        "sheet range `{}-{}` is reversed, use `{}-{}` instead",
+                        start, end, end, start
+                    ))
+                } else if *end >= sheetnames.len() {
+                    Err(format!(
+                        "sheet range `{}-{}` is not valid - only **{}** sheets avaliable!",
+                        start,
+                        end,
+                        sheetnames.len()
+                    ))
+                } else {
+                    Ok(sheetnames[*start..=*end].iter().collect())
+                }
+            }
+            selector => selector
+                .find_in(sheetnames, matching)
+                .map(|name| vec![name]),
+        }
+    }
+
+    /// Find the selected sheet, names are matched exactly first, then case-insensitively with `--ignore-case`.
+    ///
+    /// Sheets only differing by case are ambiguous for a case-insensitive match, unless `--first-match`.
+    pub fn find_in<'a>(
+        &self,
+        sheetnames: &'a [String],
+        matching: NameMatch,
+    ) -> Result<&'a String, String> {
+        match self {
+            SheetSelector::ById(id) => {
+                if *id >= sheetnames.len() {
+                    Err(format!(
+                        "sheet id `{}` is not valid - only **{}** sheets avaliable!",
+                        id,
+                        sheetnames.len()
+                    ))
+                } else {
+                    Ok(&sheetnames[*id])
+                }
+            }
+            SheetSelector::ByName(name) if name.trim().is_empty() => {
+                Err("blank sheet name, select the sheet by id instead".to_string())
+            }
+            SheetSelector::ByName(name) => {
+                if let Some(name) = sheetnames.iter().find(|s| *s == name) {
+                    Ok(name)
+                } else if let Some(name) = sheetnames
+                    .iter()
+                    .find(|s| output_name(sheetnames, s) == name.as_str())
+                {
+                    // synthesized name of a blank sheet name
+                    Ok(name)
+                } else {
+                    let folded = name.to_lowercase();
+                    let candidates: Vec<&String> = sheetnames
+                        .iter()
+                        .filter(|s| matching.ignore_case && s.to_lowercase() == folded)
+                        .collect();
+                    match candidates.as_slice() {
+                        [] => Err(format!(
+                            "sheet name `{}` is not in ({})",
+                            name,
+                            sheetnames.join(", ")
+                        )),
+                        [first] => Ok(first),
+                        [first, ..] if matching.first_match => Ok(first),
+                        candidates => Err(format!(
+                            "sheet name `{}` matches {} sheets ignoring case: {}, use the exact name or `--first-match`",
+                            name,
+                            candidates.len(),
+                            candidates
+                                .iter()
+                                .map(|s| format!("`{}`", s))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )),
+                    }
+                }
+            }
+            SheetSelector::ByRange(start, end) => {
+                match self.find_all_in(sheetnames, matching)?.as_slice() {
+                    [name] => Ok(name),
+                    _ => Err(format!(
+                        "sheet range `{}-{}` selects more than one sheet",
+                        start, end
+                    )),
+                }
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for SheetSelector {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        if let Some((start, end)) = str.split_once('-') {
+            if let (Ok(start), Ok(end)) = (start.parse(), end.parse()) {
+                return Ok(SheetSelector::ByRange(start, end));
+            }
+        }
+        match str.parse() {
+            Ok(id) => Ok(SheetSelector::ById(id)),
+            Err(_) => Ok(SheetSelector::ByName(str.to_string())),
+        }
+    }
+}
+
+/// Comma-separated list of sheet selectors, like `0-2,5,Summary`, or lists of repeated `-s`.
+#[derive(Clone, Debug)]
+pub struct SheetSelection {
+    /// Each list as given and its selectors.
+    lists: Vec<(String, Vec<SheetSelector>)>,
+}
+
+impl SheetSelection {
+    /// Find selected sheets in the list order.
+    ///
+    /// A sheet name equal to a whole list is preferred,
+    /// so names containing `,` or `-` could still be selected.
+    pub fn find_in<'a>(
+        &self,
+        sheetnames: &'a [String],
+        matching: NameMatch,
+    ) -> Result<Vec<&'a String>, String> {
+        let mut sheets = Vec::new();
+        for (raw, selectors) in &self.lists {
+            if raw.parse::<usize>().is_err() && !raw.trim().is_empty() {
+                if let Some(name) = sheetnames.iter().find(|s| *s == raw) {
+                    sheets.push(name);
+                    continue;
+                }
+            }
+            for selector in selectors {
+                sheets.extend(selector.find_all_in(sheetnames, matching)?);
+            }
+        }
+        Ok(sheets)
+    }
+
+    /// Whether the selection is `-`, to read selectors from stdin.
+    pub fn is_stdin(&self) -> bool {
+        matches!(self.lists.as_slice(), [(raw, _)] if raw == "-")
+    }
+
+    /// Join selections of repeated `-s` in order, `None` if there's none.
+    pub fn concat(selections: Vec<SheetSelection>) -> Option<Self> {
+        let lists: Vec<_> = selections.into_iter().flat_map(|s| s.lists).collect();
+        if lists.is_empty() {
+            None
+        } else {
+            Some(SheetSelection { lists })
+        }
+    }
+
+    /// Read a selection of one selector per line, blank lines are ignored.
+    ///
+    /// Lines are not split by `,`, so any sheet name could be given as a whole line.
+    pub fn from_reader<R: std::io::BufRead>(reader: R) -> Result<Self, String> {
+        let mut selectors = Vec::new();
+        for line in reader.lines() {
+            let line = line.map_err(|e| format!("read sheet selectors from stdin: {}", e))?;
+            let line = line.trim_end_matches('\r');
+            if !line.trim().is_empty() {
+                selectors.push(line.parse()?);
+            }
+        }
+        if selectors.is_empty() {
+            return Err("no sheet selectors read from stdin".to_string());
+        }
+        Ok(SheetSelection {
+            lists: vec![(String::new(), selectors)],
+        })
+    }
+}
+
+impl std::str::FromStr for SheetSelection {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let selectors = str.split(',').map(str::parse).collect::<Result<_, _>>()?;
+        Ok(SheetSelection {
+            lists: vec![(str.to_string(), selectors)],
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Delimiter(pub char);
+
+/// Delimiter represents values that can be passed from the command line that
+/// can be used as a field delimiter in CSV data.
+///
+/// Any single Unicode character is accepted, while CSV writing requires it to
+/// be a valid ASCII character, which is checked by `to_ascii` only when the
+/// delimiter is actually used for CSV output.
+impl Delimiter {
+    /// The delimiter byte for CSV writing, errors if it's not ASCII.
+    pub fn to_ascii(&self) -> Result<u8, String> {
+        if self.0.is_ascii() {
+            Ok(self.0 as u8)
+        } else {
+            Err(format!(
+                "Could not convert '{}' to ASCII delimiter, which is required by csv output.",
+                self.0
+            ))
+        }
+    }
+    pub fn as_char(&self) -> char {
+        self.0
+    }
+    pub fn to_file_extension(&self) -> String {
+        match self.0 {
+            '\t' => "tsv".into(),
+            _ => "csv".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Delimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_char())
+    }
+}
+
+impl std::str::FromStr for Delimiter {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Delimiter, Self::Err> {
+        // a layer of quotes kept by some shells, like `';'`
+        match unquote(str) {
+            r"\t" => Ok(Delimiter('\t')),
+            r"\n" => Ok(Delimiter('\n')),
+            s => {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Ok(Delimiter(c)),
+                    _ => {
+                        let msg = format!("Could not convert '{}' to a single character.", s);
+                        Err(msg)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A fast Excel-like spreadsheet to CSV coverter in Rust.
+///
+/// A simple usage like this:
+///
+/// ```sh
+/// xlsx2csv input.xlsx sheet1.csv sheet2.csv
+/// ```
+///
+/// If no output position args setted, it'll write first sheet to stdout.
+/// So the two commands are equal:
+///
+/// - `xlsx2csv input.xlsx sheet1.csv`
+///
+/// - `xlsx2csv input.xlsx > sheet1.csv`.
+///
+/// If you want to select specific sheet to stdout, use `-s/--select <id or name>` (id is 0-based):
+///
+/// `xlsx2csv input.xlsx -s 1`
+///
+/// In previous command, it'll output the second sheet to stdout.
+///
+/// If there's many sheets that you don't wanna set filename for each,
+/// use `-u` to write with sheetnames.
+///
+/// ```sh
+/// xlsx2csv input.xlsx -u
+/// ```
+///
+/// If you want to write to directory other than `.`, use `-w/--workdir` along with `-u` option.
+///
+/// ```sh
+/// xlsx2csv input.xlsx -u -w test/
+/// ```
+///
+/// The filename extension is detemined by delimiter, `,` to `.csv`, `\t` to `.tsv`, others will treat as ','.
+///
+/// By default, it will output all sheets, but if you want to select by sheet names with regex match, use `-I/--include` to include only matching, and `-X/--exclude` to exclude matching.
+/// You could also combine these two option with *include-first-exclude-after* order.
+///
+/// ```sh
+/// xlsx2csv input.xlsx -I '\S{3,}' -X 'Sheet'
+/// ```
+#[derive(Debug, StructOpt)]
+struct Opt {
+    /// Input Excel-like files, supports: .xls .xlsx .xlsb .xlsm .ods, or `-` to read from stdin.
+    xlsx: PathBuf,
+    /// Output each sheet to seprated file.
+    ///
+    /// If not setted, output first sheet to stdout. Paths ending with `.gz` are gzip-compressed.
+    /// `SHEET=PATH` writes the sheet of the name or id to the path, other paths take the remaining sheets in order.
+    output: Vec<PathBuf>,
+    /// List sheet names by id.
+    #[structopt(short, long, conflicts_with_all = &["output", "select", "use-sheet-names"])]
+    list: bool,
+    /// Format of `--list`: `names` one per line, or `table` and `json` with the id, dimensions and visibility.
+    ///
+    /// Dimensions of `table` and `json` are read by parsing every sheet.
+    #[structopt(long, requires = "list")]
+    list_format: Option<ListFormat>,
+    /// Print workbook properties (title, author, created/modified dates) and exit.
+    #[structopt(long, conflicts_with_all = &["output", "list", "select", "use-sheet-names"])]
+    properties: bool,
+    /// Print dimensions (rows and columns) of each sheet and exit.
+    ///
+    /// Output is tab-separated `sheet rows cols` lines, sheets are filtered by '-I' and '-X'.
+    #[structopt(long, conflicts_with_all = &["output", "list", "select", "use-sheet-names"])]
+    dims: bool,
+    /// Convert the selected (or first) sheet to each enabled format into null sinks, and print timings to stderr.
+    #[structopt(long, conflicts_with_all = &["output", "list", "use-sheet-names"])]
+    bench_all: bool,
+    /// Print `--properties` or `--dims` as JSON.
+    #[structopt(long)]
+    json: bool,
+    /// Pretty-print each row object of the `json-workbook` and `json` formats across multiple lines, for human reading.
+    ///
+    /// By default, every row object is compact in one line.
+    #[structopt(long)]
+    pretty: bool,
+    /// Require a sheet by name or id to exist before any conversion, exit with an error if not.
+    ///
+    /// Could be used multiple times, it doesn't select anything.
+    #[structopt(long, number_of_values = 1)]
+    require_sheet: Vec<SheetSelector>,
+    /// Select sheet by name or id in output, to stdout, to the output paths in order, or not in csv format.
+    ///
+    /// Accepts a comma-separated list of ids, names and inclusive id ranges like `0-3`,
+    /// selected sheets are written one after another in the list order.
+    /// `-` reads the selectors from stdin, one per line.
+    /// Could be used multiple times, like `-s 0 -s 2 -s HR`, sheets of all lists are written in order.
+    #[structopt(short = "s", long = "select", name = "select", number_of_values = 1)]
+    select_lists: Vec<SheetSelection>,
+    /// Selection of all `--select` lists.
+    #[structopt(skip)]
+    select: Option<SheetSelection>,
+    /// Use sheet names as output filename prefix (in current dir or --workdir).
+    #[structopt(short, long, alias = "sheet", conflicts_with = "output")]
+    use_sheet_names: bool,
+    /// What to write to stdout without `--select`: first, all or error.
+    ///
+    /// `first` writes the first sheet, `all` writes all sheets (filtered by '-I' and '-X') one after another,
+    /// `error` requires selecting sheets explicitly.
+    #[structopt(long, default_value = "first")]
+    default_action: DefaultAction,
+    /// Also write the stdout output to the file, only a single sheet is supported.
+    #[structopt(long, conflicts_with_all = &["output", "use-sheet-names"])]
+    tee: Option<PathBuf>,
+    /// Write a `# sheet: <name>` line before each sheet when more than one sheet is written to stdout.
+    #[structopt(long, conflicts_with_all = &["output", "use-sheet-names", "long"])]
+    sheet_banner: bool,
+    /// Stream the csv output to a TCP listener at `host:port` instead of stdout.
+    ///
+    /// It's for the stdout modes (selected sheets written one after another), the stream is shut down at the end.
+    #[structopt(long, conflicts_with_all = &["output", "use-sheet-names", "page"])]
+    tcp: Option<String>,
+    /// Write the sheets of `--use-sheet-names` as entries of a single ZIP archive instead of separate files.
+    ///
+    /// Entry names are the output filenames, `--workdir` is ignored.
+    #[structopt(long, requires = "use-sheet-names")]
+    zip: Option<PathBuf>,
+    /// Output files location if `--use-sheet-names` setted
+    #[structopt(short, long, conflicts_with = "output", requires = "use-sheet-names")]
+    workdir: Option<PathBuf>,
+    /// Output filename template for `--use-sheet-names`, relative to `--workdir`.
+    ///
+    /// Placeholders: `{name}` sheet name, `{index}` 0-based sheet id, `{ext}` extension by delimiter,
+    /// and `{1}`, `{2}`... or `{group}` for capture groups of `--name-from-regex`.
+    /// Missing parent directories are created, and files ending with `.gz` are gzip-compressed.
+    #[structopt(long, requires = "use-sheet-names")]
+    name_template: Option<String>,
+    /// A regex to capture parts of sheet names for `--name-template`, eg. `(\d{4})-(Q\d)`.
+    ///
+    /// Sheets not matching fall back to the default `{name}.{ext}` filename.
+    #[structopt(long, requires = "name-template")]
+    name_from_regex: Option<String>,
+    /// Skip sheets not matching `--name-from-regex` instead of the default filename.
+    #[structopt(long, requires = "name-from-regex")]
+    require_name_match: bool,
+    /// Name the output of `--use-sheet-names` after the input file when only one sheet is filtered.
+    ///
+    /// eg. `report.csv` from `report.xlsx`, it's used as `{name}` in `--name-template`.
+    #[structopt(long, requires = "use-sheet-names")]
+    name_from_input_when_single: bool,
+    /// Skip sheets of `--use-sheet-names` with the same output content as a sheet before, as duplicates.
+    ///
+    /// Each sheet is converted once more to hash its output, so it's about twice as slow.
+    #[structopt(long, requires = "use-sheet-names")]
+    dedup_sheets: bool,
+    /// Overwrite existing files of `--use-sheet-names` without asking.
+    ///
+    /// Without it, overwriting is confirmed interactively when stdin is a terminal,
+    /// otherwise files are overwritten as before.
+    #[structopt(short, long, requires = "use-sheet-names")]
+    yes: bool,
+    /// Never overwrite existing files of `--use-sheet-names`, such sheets are skipped.
+    #[structopt(long, requires = "use-sheet-names", conflicts_with = "yes")]
+    no_clobber: bool,
+    /// Print the csv files that would be written by `-u` or output paths and the sheets skipped, then exit.
+    ///
+    /// Sheets are filtered and named as in a real run, but no cells are read and no file is written.
+    #[structopt(long)]
+    dry_run: bool,
+    /// A regex pattern for matching sheetnames to include, used with '-u' or '--dims'.
+    #[structopt(short = "I", long)]
+    include: Option<String>,
+    /// A regex pattern for matching sheetnames to exclude, used with '-u' or '--dims'.
+    #[structopt(short = "X", long)]
+    exclude: Option<String>,
+    /// Regex case insensitivedly.
+    ///
+    /// When this flag is provided, the include and exclude patterns will be searched case insensitively. used with '-u' or '--dims'.
+    /// Sheet names of `-s` and `--require-sheet` are matched case insensitively too, if not matched exactly.
+    #[structopt(short = "i", long)]
+    ignore_case: bool,
+    /// Take the first sheet if a name of `-s` matches several sheets only differing by case, instead of an error.
+    #[structopt(long, requires = "ignore-case")]
+    first_match: bool,
+    /// Delimiter for output.
+    ///
+    /// If `use-sheet-names` setted, it will control the output filename extension: , -> csv, \t -> tsv.
+    /// Any single character is accepted, but csv output requires an ASCII one.
+    /// A layer of quotes around it is stripped, like `';'`.
+    #[structopt(short, long, default_value = ",")]
+    delimiter: Delimiter,
+    /// Filename extension of `--use-sheet-names` outputs (and `{ext}` of `--name-template`), like `txt`,
+    /// instead of the one by the delimiter.
+    #[structopt(long, requires = "use-sheet-names")]
+    extension: Option<String>,
+    /// End every record with a delimiter (an extra empty field), for some fixed-format importers.
+    #[structopt(long)]
+    trailing_delimiter: bool,
+    /// Always quote fields of these 0-based output columns, like `0,3` for text-like ids.
+    ///
+    /// Other columns are still quoted only when necessary.
+    #[structopt(long, conflicts_with = "quote-style")]
+    quote_cols: Option<ColumnList>,
+    /// Quoting of csv fields: necessary, always, non-numeric or never.
+    ///
+    /// `never` writes fields as is, even with delimiters or quotes in them.
+    #[structopt(long, default_value = "necessary")]
+    quote_style: QuoteStyle,
+    /// Quote character of csv fields, an ASCII one.
+    #[structopt(long, default_value = "\"")]
+    quote_char: char,
+    /// End csv records with `\r\n` instead of `\n`, for Windows consumers.
+    #[structopt(long)]
+    crlf: bool,
+    /// Write a UTF-8 BOM at the start of csv output files (not stdout), for Excel on Windows.
+    #[structopt(long, conflicts_with = "encoding")]
+    bom: bool,
+    /// Text encoding of csv outputs: utf8, utf8-bom (a BOM on stdout too) or latin1.
+    ///
+    /// `latin1` is written as Windows-1252, characters out of it are replaced with `?`.
+    #[structopt(long, default_value = "utf8")]
+    encoding: OutputEncoding,
+    /// Output format: csv, tsv, jsonl, json, html, json-workbook, sqlite, parquet, msgpack, arrow.
+    ///
+    /// `tsv` is csv with tab delimiters, like `-d '\t'`.
+    /// `jsonl` writes data rows of the selected (or first) sheets as JSON objects keyed by the first row, one per line,
+    /// and `json` writes them in a single array, to stdout or the only `output` position arg.
+    /// `html` writes a preview document with one table per sheet (the selected one or filtered by '-I' and '-X')
+    /// to stdout or the only `output` position arg.
+    /// `json-workbook` writes a JSON object of sheets (the selected ones or filtered by '-I' and '-X'),
+    /// each is an array of row objects keyed by the first row, to stdout or the only `output` position arg.
+    /// `sqlite` writes each sheet (filtered by '-I' and '-X') as a table to the database in the only `output` position arg.
+    /// `parquet` writes the selected (or first) sheet with inferred column types to the only `output` position arg.
+    /// `msgpack` writes the selected (or first) sheet as a MessagePack array of rows (see `--records`)
+    /// to stdout or the only `output` position arg.
+    /// `arrow` writes the selected (or first) sheet like `parquet`, as an Arrow IPC file.
+    #[structopt(long, default_value = "csv")]
+    format: Format,
+    /// Write rows of the `msgpack` format as maps keyed by the first row, instead of arrays of all rows.
+    #[structopt(long)]
+    #[cfg_attr(not(feature = "msgpack"), allow(dead_code))]
+    records: bool,
+    /// Number of data rows sampled to infer column types of typed formats (sqlite, parquet, arrow) and `--emit-ddl`,
+    /// all rows by default.
+    #[structopt(long)]
+    infer_rows: Option<NonZeroUsize>,
+    /// Write a `CREATE TABLE` statement per converted sheet in a SQL dialect: postgres, mysql or sqlite.
+    ///
+    /// Columns are named by the first row and typed by inference of the rows below, to stderr or `--ddl-file`.
+    #[structopt(long)]
+    emit_ddl: Option<ddl::Dialect>,
+    /// Write `--emit-ddl` statements to this file instead of stderr.
+    #[structopt(long, requires = "emit-ddl")]
+    ddl_file: Option<PathBuf>,
+    /// Emit a page separator every N rows, only used when output to stdout.
+    #[structopt(long, conflicts_with_all = &["output", "use-sheet-names"])]
+    page: Option<NonZeroUsize>,
+    /// Page separator line written between pages, `\f` (form-feed) by default.
+    ///
+    /// Escapes `\f`, `\t` and `\n` are recognized.
+    #[structopt(long, default_value = r"\f", allow_hyphen_values = true)]
+    page_separator: PageSeparator,
+    /// Repeat the first row (header) at the top of every page.
+    #[structopt(long, requires = "page")]
+    page_header: bool,
+    /// Stop output at the last row with any non-empty cell, dropping empty rows at the end of sheets.
+    ///
+    /// Unlike `--skip-empty-rows`, empty rows in between are kept. Honors `--whitespace-is-empty`.
+    #[structopt(long)]
+    trim_trailing_rows: bool,
+    /// Trim empty fields of csv records: none, trailing or all.
+    ///
+    /// `trailing` strips empty fields at the end of each record and drops empty rows at the end of sheets
+    /// (like `--trim-trailing-rows`). `all` also trims whitespaces of each field and strips empty fields at the start.
+    #[structopt(long, default_value = "none")]
+    trim: Trim,
+    /// Prepend a column of row numbers to csv output, see `--row-number-basis` and `--row-number-start`.
+    #[structopt(long)]
+    with_row_number: bool,
+    /// Number of the first row of `--with-row-number`.
+    ///
+    /// For the worksheet basis, it's the number of the worksheet's first row (row 1 in Excel).
+    #[structopt(long, default_value = "1")]
+    row_number_start: u64,
+    /// What row numbers count: `worksheet` numbers every row by its worksheet position,
+    /// `data` names the header column `row` and numbers the data rows below in output order.
+    #[structopt(long, default_value = "worksheet")]
+    row_number_basis: RowNumberBasis,
+    /// Prepend a column of the input file path to csv output, for tracing rows of sheets written together.
+    ///
+    /// Provenance columns are in the order of file, sheet and row number, before the data.
+    #[structopt(long)]
+    with_source_file: bool,
+    /// Header cell of the `--with-source-file` column.
+    #[structopt(long, default_value = "file")]
+    source_file_header: String,
+    /// Prepend a column of the sheet name to csv output, for tracing rows of sheets written together.
+    #[structopt(long)]
+    with_sheet_name: bool,
+    /// Header cell of the `--with-sheet-name` column, `sheet` by default or `__sheet__` with `--concat`.
+    #[structopt(long)]
+    sheet_name_header: Option<String>,
+    /// Write all sheets (filtered by `-I`/`-X`, or selected by `-s`) into a single csv, to stdout or the output path.
+    ///
+    /// A column of the sheet name is prepended to each row as by `--with-sheet-name`, headed `__sheet__`.
+    #[structopt(
+        long,
+        conflicts_with_all = &[
+            "use-sheet-names", "long", "kv-to-record", "with-meta-header", "page", "tcp", "dry-run"
+        ]
+    )]
+    concat: bool,
+    /// Header rows of `--concat`: `first` of the first non-empty sheet only, `all` of each sheet, or `none`.
+    #[structopt(long, default_value = "first")]
+    concat_header: ConcatHeader,
+    /// Write a block of `# key: value` lines before each sheet of csv output, for self-describing files.
+    ///
+    /// Lines of the source file, sheet name, size, conversion time and arguments, written as is without csv quoting.
+    /// It's skipped with `--raw`.
+    #[structopt(long, conflicts_with = "long")]
+    with_meta_header: bool,
+    /// Arguments after the program name the options are parsed from, written by `--with-meta-header`.
+    #[structopt(skip)]
+    args: Vec<String>,
+    /// Set the output width to the header row (the first row) up to its last non-empty cell.
+    ///
+    /// Data cells to the right of the header are dropped, and short rows are padded with `--na-rep`.
+    #[structopt(long)]
+    width_from_header: bool,
+    /// Reorder and select csv output columns by header names, listed one per line in the file.
+    ///
+    /// Listed columns missing in the header row are filled with `--na-rep`, columns not listed are appended
+    /// after them, or dropped by `--drop-extra-columns`.
+    #[structopt(long)]
+    column_order_file: Option<header::ColumnOrder>,
+    /// Write only the csv output columns of the header names, in the order listed, like `Name,Email,Country`.
+    ///
+    /// The header row is the first row (see `--header-row`), a name not in it is an error listing the header.
+    #[structopt(
+        long,
+        parse(try_from_str = header::ColumnOrder::from_list),
+        conflicts_with = "column-order-file"
+    )]
+    columns: Option<header::ColumnOrder>,
+    /// Remove a prefix from header cells, like `tbl_` of `tbl_id`, cells without it are unchanged.
+    ///
+    /// Only the header row (the first row, or the merged one) is changed, before `--empty-header-name`
+    /// and `--column-order-file`. Keyed outputs and `--emit-ddl` strip their column names too.
+    #[structopt(long)]
+    strip_header_prefix: Option<String>,
+    /// Drop columns not listed in `--column-order-file`.
+    #[structopt(long, requires = "column-order-file")]
+    drop_extra_columns: bool,
+    /// Fail on listed columns missing in the header row, or columns not listed (unless dropped).
+    #[structopt(long, requires = "column-order-file")]
+    strict_schema: bool,
+    /// Warn once per sheet with a sample cell if any csv value is a number in `e` notation, like `1.23E+11`.
+    ///
+    /// These are usually long ids or codes converted to text in scientific notation before reaching the workbook,
+    /// with the trailing digits already lost.
+    #[structopt(long)]
+    warn_scientific: bool,
+    /// Write float cells with a fixed number of decimals, like `2` for `1.50`.
+    ///
+    /// By default floats are written in the shortest plain decimals, integer-valued ones without `.0`.
+    #[structopt(long)]
+    float_precision: Option<usize>,
+    /// Strip trailing zeros (and a trailing `.`) of floats written by `--float-precision`.
+    #[structopt(long, requires = "float-precision")]
+    no_float_trailing_zeros: bool,
+    /// Write integer-valued floats without decimals with `--float-precision`, so ids like `12345` stay `12345`.
+    #[structopt(long, requires = "float-precision")]
+    int_like_floats: bool,
+    /// Combine the first N rows into a single header row, joining non-empty cells of each column.
+    #[structopt(long)]
+    merge_header_rows: Option<NonZeroUsize>,
+    /// Separator to join header cells of `--merge-header-rows`.
+    #[structopt(long, default_value = " / ")]
+    header_join: String,
+    /// Take the row right under the header as a units row (like `m`, `kg`), and `drop` it
+    /// or `merge` it into the header as `name (unit)`.
+    #[structopt(long)]
+    units_row: Option<UnitsRow>,
+    /// Separator between names and units of `--units-row merge`.
+    #[structopt(long, default_value = " ")]
+    units_join: String,
+    /// Keep units dropped by `--units-row drop` as a metadata line before the header, prefixed with `#`.
+    #[structopt(long, requires = "units-row")]
+    units_keep: bool,
+    /// Name empty cells of the header (first) row, eg. `column_{index}` or `column_{letter}`.
+    ///
+    /// `{index}` is the 1-based column index, `{letter}` is the A1 column letter in the worksheet.
+    /// Typed formats (sqlite, parquet, arrow) always name empty headers, `column_{index}` by default.
+    #[structopt(long)]
+    empty_header_name: Option<EmptyHeaderName>,
+    /// Output one `row,col,value` record per non-empty cell (long format) instead of the sheet layout.
+    ///
+    /// When output to stdout without `--select`, all sheets (filtered by '-I' and '-X') are written
+    /// as `sheet,row,col,value` records.
+    #[structopt(long, conflicts_with = "page")]
+    long: bool,
+    /// Write a two-column `key | value` sheet as a single record, a header row of keys and a row of values.
+    ///
+    /// The sheet should have exactly two non-empty columns, or specify them by `--kv-cols`.
+    /// Empty keys are named by `--empty-header-name` and repeated ones are made unique, like `key_2`.
+    #[structopt(long, conflicts_with = "long")]
+    kv_to_record: bool,
+    /// The 0-based key and value columns of `--kv-to-record`, like `0,2`.
+    #[structopt(long, requires = "kv-to-record")]
+    kv_cols: Option<ColumnList>,
+    /// Use A1 style positions in long format, eg. `B` for column 1 and `1` for row 0.
+    #[structopt(long, requires = "long")]
+    long_a1: bool,
+    /// Swap rows and columns of sheets, for sheets with fields in rows and records in columns.
+    ///
+    /// Sheets are transposed as they are read, so all row and column options apply to the transposed sheet,
+    /// and the whole sheet is kept in memory.
+    #[structopt(long)]
+    transpose: bool,
+    /// Only write the cells within an A1-style range of sheets, like `A1:D100`, the columns `B:D`,
+    /// or `A1:` to the last row and column.
+    ///
+    /// Cells keep their worksheet positions, so `--format-range` and column names still refer to them.
+    /// It applies before `--transpose` and all row and column options.
+    #[structopt(long)]
+    range: Option<cell_range::CellRange>,
+    /// Drop the first N rows of sheets before any output, like junk title rows above the header.
+    ///
+    /// Rows are counted from the first row of the used range, before other row options.
+    #[structopt(long, default_value = "0")]
+    skip_rows: usize,
+    /// The 1-based worksheet row of the header, like `3` for sheets with two preamble rows above it, or `auto`.
+    ///
+    /// Rows above it are dropped as `--skip-rows`, but it's the row number shown by Excel,
+    /// not counted from the first row of the used range. `auto` takes the first of the widest rows
+    /// with text cells only among the first 20 rows, or the first row if there's none.
+    #[structopt(long, conflicts_with = "skip-rows")]
+    header_row: Option<HeaderRow>,
+    /// Drop the first N columns of sheets before any output, counted from the first column of the used range.
+    #[structopt(long, default_value = "0")]
+    skip_cols: usize,
+    /// Write at most N data rows of each sheet below the header row(s), for a quick look at huge sheets.
+    ///
+    /// Rows are counted after `--skip-rows` and `--skip-empty-rows`. Not for `--long`.
+    #[structopt(long)]
+    max_rows: Option<usize>,
+    /// Write only the last N data rows of each sheet below the header row(s), counted like `--max-rows`.
+    #[structopt(long, conflicts_with = "max-rows")]
+    tail: Option<usize>,
+    /// Skip rows without any non-empty cell.
+    #[structopt(long)]
+    skip_empty_rows: bool,
+    /// Treat string cells of only whitespaces (or a stray BOM) as empty for `--skip-empty-rows`
+    /// and `--trim-trailing-rows`.
+    #[structopt(long)]
+    whitespace_is_empty: bool,
+    /// Limit of total bytes written to all outputs, abort when exceeded.
+    ///
+    /// The output file that hits the limit is removed, files written before are kept.
+    #[structopt(long)]
+    max_total_bytes: Option<u64>,
+    /// Date system for date cells: 1900, 1904 or auto.
+    ///
+    /// Date cells are written in ISO 8601, `auto` uses the date system detected from the workbook,
+    /// force `1900` or `1904` if dates are shifted by about 4 years.
+    #[structopt(long, default_value = "auto")]
+    date_system: DateSystem,
+    /// Strftime pattern of date cells, like `%d/%m/%Y %H:%M`, instead of ISO 8601.
+    ///
+    /// It applies to dates with or without a time part, durations are still serial numbers.
+    #[structopt(long)]
+    date_format: Option<DateFormat>,
+    /// How error cells like `#N/A` and `#DIV/0!` are written: `text` of the Excel error, or `empty`.
+    ///
+    /// Typed formats (json, sqlite, parquet, arrow and msgpack) always write them as nulls.
+    #[structopt(long, default_value = "text")]
+    error_as: ErrorAs,
+    /// How boolean cells are written: `lower` (true/false), `upper` (TRUE/FALSE like Excel) or `numeric` (1/0).
+    ///
+    /// Typed formats (json, sqlite, parquet, arrow and msgpack) keep them as booleans.
+    #[structopt(long, default_value = "lower")]
+    bool_format: BoolFormat,
+    /// Text of true cells, instead of the one by `--bool-format`.
+    #[structopt(long)]
+    true_string: Option<String>,
+    /// Text of false cells, instead of the one by `--bool-format`.
+    #[structopt(long)]
+    false_string: Option<String>,
+    /// Write cell values of csv output exactly as calamine displays them, bypassing all value formatting options.
+    ///
+    /// Dates are serial numbers and errors are their text, fields are only quoted when necessary.
+    #[structopt(
+        long,
+        conflicts_with_all = &[
+            "date-format", "format-range", "map-values", "pad-numeric", "quote-cols",
+            "unescape-html", "unquote-values", "normalize-cell-newlines", "true-string", "false-string",
+            "fill-empty", "trim", "quote-style",
+        ]
+    )]
+    raw: bool,
+    /// Map values of a column to labels, like `2:1=active,2=inactive,3=pending`.
+    ///
+    /// The column index is 0-based. Numeric cells match keys by number, strings and bools by text.
+    /// Could be used multiple times for different columns.
+    #[structopt(long, number_of_values = 1)]
+    map_values: Vec<ValueMap>,
+    /// Format cells of a worksheet range in csv output, like `B2:B100=date:%Y-%m-%d`.
+    ///
+    /// Formats are `date[:PATTERN]` (strftime, `%Y-%m-%d` by default), `text` and `number[:DECIMALS]`.
+    /// Ranges are like `B2`, `B2:D100` or whole columns `B:D`. It overrides other value options
+    /// for these cells, and the last one wins if ranges overlap. Could be used multiple times.
+    #[structopt(long, number_of_values = 1)]
+    format_range: Vec<format_range::FormatRange>,
+    /// Pad numeric cells of a column to a fixed width, like `0:6:0` for `000042`, as `COL:WIDTH:CHAR`.
+    ///
+    /// The column index is 0-based, the pad character is `0` by default and the sign stays in front of zeros.
+    /// Mapped values are not padded. Could be used multiple times for different columns.
+    #[structopt(long, number_of_values = 1)]
+    pad_numeric: Vec<PadNumeric>,
+    /// What to do with numbers wider than the `--pad-numeric` width: `error` or `truncate` to the first WIDTH characters.
+    #[structopt(long, default_value = "error")]
+    pad_overflow: PadOverflow,
+    /// Replace values not matched by `--map-values` with `--na-rep`.
+    #[structopt(long)]
+    strict_map: bool,
+    /// Representation for missing or unmapped values.
+    #[structopt(long, default_value = "")]
+    na_rep: String,
+    /// Write blank cells as this string, like `NULL`, instead of empty fields.
+    ///
+    /// Only truly absent cells (and errors with `--error-as empty`) are filled,
+    /// string cells of an empty text are kept empty. It's for csv output, typed formats still write nulls.
+    #[structopt(long)]
+    fill_empty: Option<String>,
+    /// Decode HTML entities in string cells, like `&amp;` and `&#233;`, malformed ones are kept as-is.
+    #[structopt(long)]
+    unescape_html: bool,
+    /// Strip a single layer of matching quotes (`"x"` or `'x'`) around string cells, unmatched ones are kept.
+    ///
+    /// It's about the cell content, like values quoted by some exporters, not the CSV quoting.
+    #[structopt(long)]
+    unquote_values: bool,
+    /// Rewrite line endings (`\r\n`, `\r` or `\n`) inside string cells to `lf`, `crlf`, a `space` or `remove` them.
+    ///
+    /// It's about embedded newlines of cells, not the record terminator of output. Unchanged by default.
+    #[structopt(long)]
+    normalize_cell_newlines: Option<CellNewline>,
+    /// Code page to decode strings of legacy .xls input, eg. `1251`, `windows-1251` or `shift_jis`.
+    ///
+    /// A last resort for mojibake when the code page declared in the file is missing or wrong.
+    #[structopt(long)]
+    xls_codepage: Option<XlsCodepage>,
+    /// Format of the input workbook: xlsx, xls, ods or xlsb.
+    ///
+    /// By default it's by the file extension, or detected from the content of stdin and unknown extensions.
+    #[structopt(long)]
+    input_format: Option<InputFormat>,
+    /// What to do when a sheet fails to parse: `abort` exits with an error, `skip-sheet` converts it as empty,
+    /// `emit-partial` converts the cells read before the failure (.xlsx and .xlsb only, others are skipped).
+    ///
+    /// Other sheets are still converted, errors and salvaged cells are reported to stderr.
+    #[structopt(long, default_value = "abort")]
+    on_parse_error: OnParseError,
+    /// Merged cells: `blank` keeps the value in the top-left cell only, `fill` copies it to every cell
+    /// of the region, `warn` keeps blanks and lists the merged regions of each sheet on stderr.
+    ///
+    /// Regions are read from .xlsx and .xls files, other formats are warned and kept as is.
+    #[structopt(long, default_value = "blank")]
+    merged: MergedCells,
+    /// Exit code when the input workbook has no sheet.
+    ///
+    /// When `--include` and `--exclude` filter out all sheets, it exits with code 6 instead.
+    #[structopt(long, default_value = "5")]
+    empty_workbook_exit_code: i32,
+    /// Show a progress bar of rows on stderr, with the rate and ETA over all sheets to write in csv format.
+    ///
+    /// Row totals come from the declared dimensions of .xlsx and .xlsb sheets, other formats are parsed up front.
+    #[structopt(long)]
+    progress_eta: bool,
+    /// Show a progress bar of the rows of each sheet on stderr while writing csv, named by the sheet.
+    ///
+    /// It's only shown if stderr is a terminal, so logs of redirected stderr stay clean.
+    #[structopt(long, conflicts_with = "progress-eta")]
+    progress: bool,
+    /// Do not cache the parsed range of the last accessed sheet.
+    #[structopt(long)]
+    no_cache: bool,
+    /// Threads to write the files of `--use-sheet-names` with, the number of CPUs by default.
+    ///
+    /// Each thread opens the workbook and parses its sheets on its own. Sheets are written one by one
+    /// with `--zip`, a shown progress bar (`--progress-eta` or `--progress`) or a workbook from stdin.
+    #[structopt(short, long)]
+    jobs: Option<NonZeroUsize>,
+    /// Print a table of per-sheet status (ok, empty, skipped or error) and rows written to stderr at the end.
+    #[structopt(long)]
+    summary: bool,
+    /// Write a csv index of `sheet,output_path,rows` for written sheets to the path, after all conversions.
+    ///
+    /// Output paths are `-` for stdout, and entry names for `--zip`. Skipped and failed sheets are not listed.
+    #[structopt(long)]
+    index_file: Option<PathBuf>,
+}
+
+impl Opt {
+    fn name_match(&self) -> NameMatch {
+        NameMatch {
+            ignore_case: self.ignore_case,
+            first_match: self.first_match,
+        }
+    }
+}
+
+/// Output format.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Csv,
+    Tsv,
+    Jsonl,
+    Json,
+    Html,
+    JsonWorkbook,
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+    #[cfg(feature = "parquet")]
+    Parquet,
+    #[cfg(feature = "msgpack")]
+    Msgpack,
+    #[cfg(feature = "arrow")]
+    Arrow,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "jsonl" => Ok(Format::Jsonl),
+            "json" => Ok(Format::Json),
+            "html" => Ok(Format::Html),
+            "json-workbook" => Ok(Format::JsonWorkbook),
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Ok(Format::Sqlite),
+            #[cfg(not(feature = "sqlite"))]
+            "sqlite" => Err("sqlite format requires the `sqlite` feature".to_string()),
+            #[cfg(feature = "parquet")]
+            "parquet" => Ok(Format::Parquet),
+            #[cfg(not(feature = "parquet"))]
+            "parquet" => Err("parquet format requires the `parquet` feature".to_string()),
+            #[cfg(feature = "msgpack")]
+            "msgpack" => Ok(Format::Msgpack),
+            #[cfg(not(feature = "msgpack"))]
+            "msgpack" => Err("msgpack format requires the `msgpack` feature".to_string()),
+            #[cfg(feature = "arrow")]
+            "arrow" => Ok(Format::Arrow),
+            #[cfg(not(feature = "arrow"))]
+            "arrow" => Err("arrow format requires the `arrow` feature".to_string()),
+            s => Err(format!(
+                "unknown format `{}`, supports: csv, tsv, jsonl, json, html, json-workbook, sqlite, parquet, msgpack, arrow",
+                s
+            )),
+        }
+    }
+}
+
+/// Date system of serial numbers in date cells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DateSystem {
+    /// Detected from the workbook.
+    Auto,
+    /// Days since 1899-12-30, which is the Windows Excel default.
+    Excel1900,
+    /// Days since 1904-01-01, used by older Mac Excel versions.
+    Excel1904,
+}
+
+impl std::str::FromStr for DateSystem {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "auto" => Ok(DateSystem::Auto),
+            "1900" => Ok(DateSystem::Excel1900),
+            "1904" => Ok(DateSystem::Excel1904),
+            s => Err(format!(
+                "unknown date system `{}`, supports: 1900, 1904, auto",
+                s
+            )),
+        }
+    }
+}
+
+/// What to write to stdout when no sheet is selected.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DefaultAction {
+    /// The first sheet.
+    First,
+    /// All sheets (filtered by '-I' and '-X') one after another.
+    All,
+    /// Nothing, it's an error.
+    Error,
+}
+
+impl std::str::FromStr for DefaultAction {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "first" => Ok(DefaultAction::First),
+            "all" => Ok(DefaultAction::All),
+            "error" => Ok(DefaultAction::Error),
+            s => Err(format!(
+                "unknown default action `{}`, supports: first, all, error",
+                s
+            )),
+        }
+    }
+}
+
+/// What to do with the units row of `--units-row`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnitsRow {
+    Drop,
+    Merge,
+}
+
+impl std::str::FromStr for UnitsRow {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "drop" => Ok(UnitsRow::Drop),
+            "merge" => Ok(UnitsRow::Merge),
+            s => Err(format!(
+                "unknown units row mode `{}`, supports: drop, merge",
+                s
+            )),
+        }
+    }
+}
+
+/// Line ending of string cells for `--normalize-cell-newlines`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CellNewline {
+    Lf,
+    Crlf,
+    Space,
+    Remove,
+}
+
+impl CellNewline {
+    /// Rewrite every `\r\n`, `\r` and `\n` line ending in `s`.
+    pub fn normalize(self, s: &str) -> String {
+        let replacement = match self {
+            CellNewline::Lf => "\n",
+            CellNewline::Crlf => "\r\n",
+            CellNewline::Space => " ",
+            CellNewline::Remove => "",
+        };
+        let mut normalized = String::with_capacity(s.len());
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' => {
+                    chars.next_if_eq(&'\n');
+                    normalized.push_str(replacement);
+                }
+                '\n' => normalized.push_str(replacement),
+                c => normalized.push(c),
+            }
+        }
+        normalized
+    }
+}
+
+impl std::str::FromStr for CellNewline {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "lf" => Ok(CellNewline::Lf),
+            "crlf" => Ok(CellNewline::Crlf),
+            "space" => Ok(CellNewline::Space),
+            "remove" => Ok(CellNewline::Remove),
+            s => Err(format!(
+                "unknown cell newline `{}`, supports: lf, crlf, space, remove",
+                s
+            )),
+        }
+    }
+}
+
+/// Quoting of csv fields, see `--quote-style`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuoteStyle {
+    Necessary,
+    Always,
+    NonNumeric,
+    Never,
+}
+
+impl std::str::FromStr for QuoteStyle {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "necessary" => Ok(QuoteStyle::Necessary),
+            "always" => Ok(QuoteStyle::Always),
+            "non-numeric" => Ok(QuoteStyle::NonNumeric),
+            "never" => Ok(QuoteStyle::Never),
+            s => Err(format!(
+                "unknown quote style `{}`, supports: necessary, always, non-numeric, never",
+                s
+            )),
+        }
+    }
+}
+
+impl From<QuoteStyle> for csv::QuoteStyle {
+    fn from(style: QuoteStyle) -> Self {
+        match style {
+            QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            QuoteStyle::Always => csv::QuoteStyle::Always,
+            QuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+            QuoteStyle::Never => csv::QuoteStyle::Never,
+        }
+    }
+}
+
+/// How to handle sheets failed to parse, eg. parts of damaged files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnParseError {
+    /// Exit with an error.
+    Abort,
+    /// Report the error and convert the sheet as empty.
+    SkipSheet,
+    /// Report the error and convert the cells read before the failure (.xlsx and .xlsb only).
+    EmitPartial,
+}
+
+impl std::str::FromStr for OnParseError {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "abort" => Ok(OnParseError::Abort),
+            "skip-sheet" => Ok(OnParseError::SkipSheet),
+            "emit-partial" => Ok(OnParseError::EmitPartial),
+            s => Err(format!(
+                "unknown parse error policy `{}`, supports: abort, skip-sheet, emit-partial",
+                s
+            )),
+        }
+    }
+}
+
+/// Handling of merged cells, see `--merged`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MergedCells {
+    /// Keep the value in the top-left cell, the others blank.
+    Blank,
+    /// Copy the top-left value to all cells of the region.
+    Fill,
+    /// Keep blanks, and warn with the merged regions of each sheet.
+    Warn,
+}
+
+impl std::str::FromStr for MergedCells {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "blank" => Ok(MergedCells::Blank),
+            "fill" => Ok(MergedCells::Fill),
+            "warn" => Ok(MergedCells::Warn),
+            s => Err(format!(
+                "unknown merged cells mode `{}`, supports: blank, fill, warn",
+                s
+            )),
+        }
+    }
+}
+
+/// Trimming of csv records, see `--trim`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trim {
+    None,
+    Trailing,
+    All,
+}
+
+impl Trim {
+    /// Trim the fields of a record.
+    fn apply(self, fields: &mut Vec<String>) {
+        if self == Trim::All {
+            for field in fields.iter_mut() {
+                let trimmed = field.trim();
+                if trimmed.len() < field.len() {
+                    *field = trimmed.to_string();
+                }
+            }
+            let leading = fields.iter().take_while(|f| f.is_empty()).count();
+            fields.drain(..leading);
+        }
+        if self != Trim::None {
+            let len = fields
+                .iter()
+                .rposition(|f| !f.is_empty())
+                .map_or(0, |i| i + 1);
+            fields.truncate(len);
+        }
+    }
+}
+
+impl std::str::FromStr for Trim {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "none" => Ok(Trim::None),
+            "trailing" => Ok(Trim::Trailing),
+            "all" => Ok(Trim::All),
+            s => Err(format!(
+                "unknown trim `{}`, supports: none, trailing, all",
+                s
+            )),
+        }
+    }
+}
+
+/// How error cells are written, see `--error-as`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorAs {
+    /// The Excel error text, like `#N/A`.
+    Text,
+    Empty,
+}
+
+impl std::str::FromStr for ErrorAs {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "text" => Ok(ErrorAs::Text),
+            "empty" => Ok(ErrorAs::Empty),
+            s => Err(format!(
+                "unknown error cell rendering `{}`, supports: text, empty",
+                s
+            )),
+        }
+    }
+}
+
+/// How boolean cells are written, see `--bool-format`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoolFormat {
+    Lower,
+    Upper,
+    Numeric,
+}
+
+impl std::str::FromStr for BoolFormat {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "lower" => Ok(BoolFormat::Lower),
+            "upper" => Ok(BoolFormat::Upper),
+            "numeric" => Ok(BoolFormat::Numeric),
+            s => Err(format!(
+                "unknown bool format `{}`, supports: lower, upper, numeric",
+                s
+            )),
+        }
+    }
+}
+
+/// Text of a boolean cell, by `--true-string`/`--false-string` or `--bool-format`.
+fn format_bool(b: bool, opt: &Opt) -> &str {
+    let custom = if b {
+        &opt.true_string
+    } else {
+        &opt.false_string
+    };
+    if let Some(s) = custom {
+        return s;
+    }
+    match (opt.bool_format, b) {
+        (BoolFormat::Lower, true) => "true",
+        (BoolFormat::Lower, false) => "false",
+        (BoolFormat::Upper, true) => "TRUE",
+        (BoolFormat::Upper, false) => "FALSE",
+        (BoolFormat::Numeric, true) => "1",
+        (BoolFormat::Numeric, false) => "0",
+    }
+}
+
+/// What the numbers of `--with-row-number` count.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RowNumberBasis {
+    /// Worksheet rows, every row is numbered by its position in the worksheet.
+    Worksheet,
+    /// Data rows, the first row is the header and the rows below are numbered in order.
+    Data,
+}
+
+impl std::str::FromStr for RowNumberBasis {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "worksheet" => Ok(RowNumberBasis::Worksheet),
+            "data" => Ok(RowNumberBasis::Data),
+            s => Err(format!(
+                "unknown row number basis `{}`, supports: worksheet, data",
+                s
+            )),
+        }
+    }
+}
+
+/// Map values in a column to labels, like `2:1=active,2=inactive`.
+#[derive(Clone, Debug)]
+pub struct ValueMap {
+    pub column: usize,
+    pub entries: Vec<(String, String)>,
+}
+
+impl ValueMap {
+    /// Find the label for a cell.
+    ///
+    /// Numeric cells match keys by number (`1` matches `1.0`), strings and
+    /// bools match keys by text, other cells never match.
+    pub fn lookup(&self, cell: &Data) -> Option<&str> {
+        let number = match *cell {
+            Data::Int(i) => Some(i as f64),
+            Data::Float(f) => Some(f),
+            _ => None,
+        };
+        self.entries
+            .iter()
+            .find(|(key, _)| match (cell, number) {
+                (_, Some(n)) => key.parse::<f64>().map(|k| k == n).unwrap_or(false),
+                (Data::String(s), _) => key == s,
+                (Data::Bool(b), _) => *key == b.to_string(),
+                _ => false,
+            })
+            .map(|(_, label)| label.as_str())
+    }
+}
+
+impl std::str::FromStr for ValueMap {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let (column, mapping) = str
+            .split_once(':')
+            .ok_or_else(|| format!("value map `{}` should be like `COL:KEY=LABEL,...`", str))?;
+        let column = column
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid column index `{}` in value map", column))?;
+        let entries = mapping
+            .split(',')
+            .map(|entry| {
+                entry
+                    .split_once('=')
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .ok_or_else(|| {
+                        format!("value map entry `{}` should be like `KEY=LABEL`", entry)
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ValueMap { column, entries })
+    }
+}
+
+/// Pad numeric cells in a column to a fixed width, like `0:6:0`.
+#[derive(Clone, Debug)]
+pub struct PadNumeric {
+    pub column: usize,
+    pub width: usize,
+    pub fill: char,
+}
+
+impl PadNumeric {
+    /// Pad a formatted number, `Err` with the value if it is wider than the width.
+    pub fn pad(&self, value: String) -> Result<String, String> {
+        let len = value.chars().count();
+        if len > self.width {
+            return Err(value);
+        }
+        let padding: String = std::iter::repeat_n(self.fill, self.width - len).collect();
+        match value.strip_prefix('-') {
+            Some(digits) if self.fill == '0' => Ok(format!("-{}{}", padding, digits)),
+            _ => Ok(padding + &value),
+        }
+    }
+}
+
+impl std::str::FromStr for PadNumeric {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("pad `{}` should be like `COL:WIDTH:CHAR`", str);
+        let mut parts = str.splitn(3, ':');
+        let column = parts.next().ok_or_else(invalid)?;
+        let column = column
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid column index `{}` in pad", column))?;
+        let width = parts.next().ok_or_else(invalid)?;
+        let width = width
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid width `{}` in pad", width))?;
+        let fill = match parts.next() {
+            None => '0',
+            Some(fill) => {
+                let mut chars = fill.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => return Err(format!("pad character `{}` should be a single char", fill)),
+                }
+            }
+        };
+        Ok(PadNumeric {
+            column,
+            width,
+            fill,
+        })
+    }
+}
+
+/// Handling of numbers wider than `--pad-numeric`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PadOverflow {
+    Error,
+    Truncate,
+}
+
+impl std::str::FromStr for PadOverflow {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "error" => Ok(PadOverflow::Error),
+            "truncate" => Ok(PadOverflow::Truncate),
+            s => Err(format!(
+                "unknown pad overflow `{}`, supports: error, truncate",
+                s
+            )),
+        }
+    }
+}
+
+/// Header row of `--header-row`, a 1-based worksheet row or detected in each sheet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HeaderRow {
+    Row(NonZeroUsize),
+    Auto,
+}
+
+impl std::str::FromStr for HeaderRow {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "auto" => Ok(HeaderRow::Auto),
+            s => s
+                .parse()
+                .map(HeaderRow::Row)
+                .map_err(|_| format!("invalid header row `{}`, a 1-based row number or `auto`", s)),
+        }
+    }
+}
+
+/// Page separator for stdout paging, with common escapes decoded.
+#[derive(Clone, Debug)]
+pub struct PageSeparator(pub String);
+
+impl std::str::FromStr for PageSeparator {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let sep = str
+            .replace(r"\f", "\x0c")
+            .replace(r"\t", "\t")
+            .replace(r"\n", "\n");
+        Ok(PageSeparator(sep))
+    }
+}
+
+/// Windows code page identifier of legacy .xls strings, from a number or an encoding label.
+#[derive(Clone, Copy, Debug)]
+pub struct XlsCodepage(pub u16);
+
+impl std::str::FromStr for XlsCodepage {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        if let Ok(codepage) = str.parse() {
+            return match codepage::to_encoding(codepage) {
+                Some(_) => Ok(XlsCodepage(codepage)),
+                None => Err(format!("unsupported code page `{}`", codepage)),
+            };
+        }
+        let encoding = encoding_rs::Encoding::for_label(str.trim().as_bytes())
+            .ok_or_else(|| format!("unknown encoding `{}`", str))?;
+        (1..=u16::MAX)
+            .find(|cp| codepage::to_encoding(*cp) == Some(encoding))
+            .map(XlsCodepage)
+            .ok_or_else(|| format!("no code page for encoding `{}`", encoding.name()))
+    }
+}
+
+/// Name template of empty header cells, with `{index}` (1-based) and `{letter}` (A1 column) placeholders.
+#[derive(Clone, Debug)]
+pub struct EmptyHeaderName(String);
+
+impl Default for EmptyHeaderName {
+    fn default() -> Self {
+        EmptyHeaderName("column_{index}".to_string())
+    }
+}
+
+impl EmptyHeaderName {
+    /// Name of the empty header cell at the 0-based `col` of output, which is `sheet_col` in the worksheet.
+    pub fn render(&self, col: usize, sheet_col: usize) -> String {
+        self.0
+            .replace("{index}", &(col + 1).to_string())
+            .replace("{letter}", &column_name(sheet_col))
+    }
+}
+
+impl std::str::FromStr for EmptyHeaderName {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let rest = str.replace("{index}", "").replace("{letter}", "");
+        if rest.contains('{') || rest.contains('}') {
+            return Err(format!(
+                "unknown placeholder in `{}`, supports: {{index}}, {{letter}}",
+                str
+            ));
+        }
+        if str.trim().is_empty() {
+            return Err("empty header name should not be empty".to_string());
+        }
+        Ok(EmptyHeaderName(str.to_string()))
+    }
+}
+
+/// Strftime pattern of date cells, validated when parsed.
+#[derive(Clone, Debug)]
+pub struct DateFormat(pub String);
+
+impl std::str::FromStr for DateFormat {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        use chrono::format::{Item, StrftimeItems};
+        if StrftimeItems::new(str).any(|item| item == Item::Error) {
+            return Err(format!("invalid date format `{}`", str));
+        }
+        Ok(DateFormat(str.to_string()))
+    }
+}
+
+/// Comma-separated list of 0-based output column indexes, like `0,3`.
+#[derive(Clone, Debug)]
+pub struct ColumnList(pub Vec<usize>);
+
+impl ColumnList {
+    pub fn contains(&self, col: usize) -> bool {
+        self.0.contains(&col)
+    }
+}
+
+impl std::str::FromStr for ColumnList {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        str.split(',')
+            .map(|col| {
+                col.trim()
+                    .parse()
+                    .map_err(|_| format!("invalid column index `{}`", col))
+            })
+            .collect::<Result<_, _>>()
+            .map(ColumnList)
+    }
+}
+
+/// Paging settings used when writing a sheet to stdout.
+#[derive(Clone, Debug)]
+pub struct Paging {
+    pub rows: usize,
+    pub separator: String,
+    pub repeat_header: bool,
+}
+
+fn format_cell(cell: &Data, opt: &Opt) -> String {
+    let mut out = String::new();
+    push_cell(&mut out, cell, opt);
+    out
+}
+
+/// Append a formatted cell to `out`, so buffers could be reused for rows.
+fn push_cell(out: &mut String, cell: &Data, opt: &Opt) {
+    use std::fmt::Write;
+    // writing to a String never fails
+    let _ = match *cell {
+        Data::Int(ref c) => write!(out, "{}", c),
+        Data::Float(c) => push_float(out, c, opt),
+        Data::String(ref c) => {
+            push_string(out, c, opt);
+            Ok(())
+        }
+        Data::Bool(c) => {
+            out.push_str(format_bool(c, opt));
+            Ok(())
+        }
+        Data::DateTime(ref c) => {
+            out.push_str(&format_datetime(
+                c,
+                opt.date_system,
+                opt.date_format.as_ref(),
+            ));
+            Ok(())
+        }
+        Data::DateTimeIso(ref c) | Data::DurationIso(ref c) => {
+            out.push_str(c);
+            Ok(())
+        }
+        Data::Error(ref e) if opt.error_as == ErrorAs::Text => write!(out, "{}", e),
+        _ => Ok(()),
+    };
+}
+
+/// Append a float cell, with `--float-precision` decimals if set.
+fn push_float(out: &mut String, f: f64, opt: &Opt) -> std::fmt::Result {
+    use std::fmt::Write;
+    match opt.float_precision {
+        Some(_) if opt.int_like_floats && f.fract() == 0.0 => write!(out, "{}", f),
+        Some(precision) => {
+            let start = out.len();
+            write!(out, "{:.*}", precision, f)?;
+            if opt.no_float_trailing_zeros && out[start..].contains('.') {
+                let len = out.trim_end_matches('0').trim_end_matches('.').len();
+                out.truncate(len);
+            }
+            Ok(())
+        }
+        None => write!(out, "{}", f),
+    }
+}
+
+/// Append a string cell with `--unescape-html`, `--unquote-values` and `--normalize-cell-newlines`, in this order.
+fn push_string(out: &mut String, s: &str, opt: &Opt) {
+    let s = if opt.unescape_html {
+        html_escape::decode_html_entities(s)
+    } else {
+        Cow::Borrowed(s)
+    };
+    let s = if opt.unquote_values { unquote(&s) } else { &s };
+    match opt.normalize_cell_newlines {
+        Some(newline) => out.push_str(&newline.normalize(s)),
+        None => out.push_str(s),
+    }
+}
+
+/// Strip a single layer of matching double or single quotes around the whole string.
+fn unquote(s: &str) -> &str {
+    for quote in ['"', '\''] {
+        if s.len() >= 2 && s.starts_with(quote) && s.ends_with(quote) {
+            return &s[1..s.len() - 1];
+        }
+    }
+    s
+}
+
+/// Format date cells in ISO 8601, `2021-03-14` or `2021-03-14T09:30:00` if there's a time part,
+/// or by the `--date-format` pattern.
+///
+/// Durations are kept as serial numbers.
+fn format_datetime(
+    datetime: &ExcelDateTime,
+    system: DateSystem,
+    format: Option<&DateFormat>,
+) -> String {
+    if datetime.is_duration() {
+        return format!("{}", datetime.as_f64());
+    }
+    let datetime = in_date_system(datetime, system);
+    match (datetime.as_datetime(), format) {
+        (Some(dt), Some(format)) => dt.format(&format.0).to_string(),
+        (Some(dt), None) if dt.time() == chrono::NaiveTime::MIN => {
+            dt.format("%Y-%m-%d").to_string()
+        }
+        (Some(dt), None) => dt.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        (None, _) => format!("{}", datetime.as_f64()),
+    }
+}
+
+/// Date cell read in the date system, or the detected one for `Auto`.
+fn in_date_system(datetime: &ExcelDateTime, system: DateSystem) -> ExcelDateTime {
+    match system {
+        DateSystem::Auto => *datetime,
+        DateSystem::Excel1900 => {
+            ExcelDateTime::new(datetime.as_f64(), ExcelDateTimeType::DateTime, false)
+        }
+        DateSystem::Excel1904 => {
+            ExcelDateTime::new(datetime.as_f64(), ExcelDateTimeType::DateTime, true)
+        }
+    }
+}
+
+/// Check if all cells of a row are empty.
+///
+/// With `whitespace` set, string cells of only whitespaces or BOM are treated as empty too.
+fn is_empty_row(row: &[Data], whitespace: bool) -> bool {
+    row.iter().all(|c| match c {
+        Data::Empty => true,
+        Data::String(s) if whitespace => s.chars().all(|c| c.is_whitespace() || c == '\u{feff}'),
+        _ => false,
+    })
+}
+
+/// Rows of a range to output, cut by `--skip-rows` and `--skip-cols`,
+/// and filtered by `--trim-trailing-rows` (or `--trim`) and `--skip-empty-rows`.
+fn data_rows<'a>(range: &'a Range<Data>, opt: &'a Opt) -> impl Iterator<Item = &'a [Data]> + 'a {
+    indexed_data_rows(range, opt).map(|(_, row)| row)
+}
+
+/// Rows of `data_rows` with their 0-based indexes in the range.
+fn indexed_data_rows<'a>(
+    range: &'a Range<Data>,
+    opt: &'a Opt,
+) -> impl Iterator<Item = (usize, &'a [Data])> + 'a {
+    let skip_cols = opt.skip_cols;
+    let cells = move |row: &'a [Data]| &row[skip_cols.min(row.len())..];
+    let height = if opt.trim_trailing_rows || opt.trim != Trim::None {
+        // whitespaces are trimmed to empty fields by `--trim all`
+        let whitespace_is_empty = opt.whitespace_is_empty || opt.trim == Trim::All;
+        range
+            .rows()
+            .rposition(|row| !is_empty_row(cells(row), whitespace_is_empty))
+            .map_or(0, |i| i + 1)
+    } else {
+        range.height()
+    };
+    let rows = range
+        .rows()
+        .take(height)
+        .map(cells)
+        .enumerate()
+        .skip(skipped_rows(range, opt))
+        .filter(move |(_, row)| {
+            !(opt.skip_empty_rows && is_empty_row(row, opt.whitespace_is_empty))
+        });
+    // data rows of `--max-rows` or `--tail` below the header rows, as (skipped, taken)
+    let head = header_rows(opt);
+    let (skip, take) = match (opt.max_rows, opt.tail) {
+        (Some(max), _) => (0, max),
+        (None, Some(tail)) => {
+            let total = rows.clone().count().saturating_sub(head);
+            (total.saturating_sub(tail), tail)
+        }
+        (None, None) => (0, usize::MAX),
+    };
+    rows.enumerate()
+        .take(head.saturating_add(skip).saturating_add(take))
+        .filter(move |(k, _)| *k < head || *k >= head + skip)
+        .map(|(_, row)| row)
+}
+
+/// Number of header rows on top of sheets, the merged ones of `--merge-header-rows` and the `--units-row`.
+fn header_rows(opt: &Opt) -> usize {
+    opt.merge_header_rows.map_or(1, NonZeroUsize::get) + opt.units_row.is_some() as usize
+}
+
+/// Worksheet position of the first cell of `data_rows`, the range start moved by `--skip-cols`.
+fn data_start(range: &Range<Data>, opt: &Opt) -> (u32, u32) {
+    let (row, col) = range.start().unwrap_or((0, 0));
+    (row, col + opt.skip_cols as u32)
+}
+
+/// Number of rows dropped from the top of a range, by `--skip-rows` or `--header-row`.
+fn skipped_rows(range: &Range<Data>, opt: &Opt) -> usize {
+    match opt.header_row {
+        Some(HeaderRow::Row(row)) => {
+            let first = range.start().map_or(0, |(row0, _)| row0 as usize);
+            (row.get() - 1).saturating_sub(first)
+        }
+        Some(HeaderRow::Auto) => detect_header_row(range, opt.skip_cols),
+        None => opt.skip_rows,
+    }
+}
+
+/// Index of the header row of `--header-row auto` in a range, with the first `skip_cols` cells of rows ignored.
+///
+/// It's the first of the rows with the most non-empty cells among the first 20 ones, only counting rows
+/// without numbers, dates or other non-text cells. Titles and notes above a header usually have fewer cells.
+fn detect_header_row(range: &Range<Data>, skip_cols: usize) -> usize {
+    let mut best = (0, 0);
+    for (i, row) in range.rows().take(20).enumerate() {
+        let cells = &row[skip_cols.min(row.len())..];
+        let all_text = cells
+            .iter()
+            .all(|c| matches!(c, Data::Empty | Data::String(_)));
+        let texts = cells
+            .iter()
+            .filter(|c| matches!(c, Data::String(s) if !s.trim().is_empty()))
+            .count();
+        if all_text && texts > best.1 {
+            best = (i, texts);
+        }
+    }
+    best.0
+}
+
+/// Format a header cell in column `col`, as `render_cell` but never mapped by `--map-values`.
+fn render_header_cell(col: usize, cell: &Data, opt: &Opt) -> std::io::Result<String> {
+    pad_cell(col, cell, opt)
+}
+
+/// Format a cell in column `col` with the value mapping and padding options.
+fn render_cell(col: usize, cell: &Data, opt: &Opt) -> std::io::Result<String> {
+    match opt.map_values.iter().find(|m| m.column == col) {
+        Some(map) => match map.lookup(cell) {
+            Some(label) => Ok(label.to_string()),
+            None if opt.strict_map => Ok(opt.na_rep.clone()),
+            None => pad_cell(col, cell, opt),
+        },
+        None => pad_cell(col, cell, opt),
+    }
+}
+
+/// Format a cell with the `--pad-numeric` of column `col` if it's a number.
+///
+/// Overflowed numbers are an error, unless `--pad-overflow truncate`.
+fn pad_cell(col: usize, cell: &Data, opt: &Opt) -> std::io::Result<String> {
+    let value = format_cell(cell, opt);
+    let pad = match (cell, opt.pad_numeric.iter().find(|p| p.column == col)) {
+        (Data::Int(_) | Data::Float(_), Some(pad)) => pad,
+        _ => return Ok(value),
+    };
+    match pad.pad(value) {
+        Ok(padded) => Ok(padded),
+        Err(value) if opt.pad_overflow == PadOverflow::Truncate => {
+            Ok(value.chars().take(pad.width).collect())
+        }
+        Err(value) => {
+            let msg = format!(
+                "value `{}` in column {} is wider than the pad width {}, see --pad-overflow",
+                value, col, pad.width
+            );
+            Err(std::io::Error::new(std::io::ErrorKind::InvalidData, msg))
+        }
+    }
+}
+
+/// Format a cell at the 0-based worksheet position, by the last `--format-range` containing it if any,
+/// or `render_cell` of the output column `col` otherwise.
+fn render_cell_at(
+    row: u32,
+    sheet_col: u32,
+    col: usize,
+    cell: &Data,
+    opt: &Opt,
+) -> std::io::Result<String> {
+    let mut out = String::new();
+    push_cell_at(&mut out, row, sheet_col, col, cell, opt, false)?;
+    Ok(out)
+}
+
+/// Append a cell formatted as `render_cell_at` to `out`, or as it's displayed by calamine with `--raw`.
+///
+/// Cells of `header` rows are not mapped by `--map-values`, see `render_header_cell`.
+fn push_cell_at(
+    out: &mut String,
+    row: u32,
+    sheet_col: u32,
+    col: usize,
+    cell: &Data,
+    opt: &Opt,
+    header: bool,
+) -> std::io::Result<()> {
+    if opt.raw {
+        use std::fmt::Write;
+        let _ = write!(out, "{}", cell);
+        return Ok(());
+    }
+    if let Some(fill) = &opt.fill_empty {
+        let blank = match cell {
+            Data::Empty => true,
+            Data::Error(_) => opt.error_as == ErrorAs::Empty,
+            _ => false,
+        };
+        if blank {
+            out.push_str(fill);
+            return Ok(());
+        }
+    }
+    let format = opt
+        .format_range
+        .iter()
+        .rev()
+        .find(|f| f.contains(row, sheet_col));
+    if let Some(format) = format {
+        out.push_str(&format.render(cell, opt));
+    } else if header && opt.pad_numeric.iter().any(|p| p.column == col) {
+        out.push_str(&render_header_cell(col, cell, opt)?);
+    } else if !header
+        && (opt.map_values.iter().any(|m| m.column == col)
+            || opt.pad_numeric.iter().any(|p| p.column == col))
+    {
+        out.push_str(&render_cell(col, cell, opt)?);
+    } else {
+        push_cell(out, cell, opt);
+    }
+    Ok(())
+}
+
+/// Render cells of a row at the 0-based worksheet position into `cols`, reusing its strings.
+fn render_row_into(
+    cols: &mut Vec<String>,
+    row: u32,
+    col0: u32,
+    cells: &[Data],
+    opt: &Opt,
+    header: bool,
+) -> std::io::Result<()> {
+    cols.resize_with(cells.len(), String::new);
+    for (j, (cell, out)) in cells.iter().zip(cols.iter_mut()).enumerate() {
+        out.clear();
+        push_cell_at(out, row, col0 + j as u32, j, cell, opt, header)?;
+    }
+    Ok(())
+}
+
+/// Column name in A1 notation of a 0-based column index, eg. `0` to `A`, `27` to `AB`.
+fn column_name(col: usize) -> String {
+    let mut name = Vec::new();
+    let mut n = col + 1;
+    while n > 0 {
+        name.push(b'A' + ((n - 1) % 26) as u8);
+        n = (n - 1) / 26;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap()
+}
+
+/// Write non-empty cells of a sheet in long format, one `row,col,value` record per cell.
+///
+/// Positions are 0-based worksheet indices, or A1 style with `--long-a1`.
+/// The `sheet_column` value is prepended to each record if set.
+/// Returns the number of records written.
+fn worksheet_to_long<W: std::io::Write>(
+    workbook: &mut Workbook,
+    sheet: &str,
+    wtr: &mut csv::Writer<W>,
+    opt: &Opt,
+    sheet_column: Option<&str>,
+    progress: &Progress,
+) -> csv::Result<usize> {
+    progress.start_sheet(sheet);
+    let range = workbook.range(sheet)?;
+    let (row0, col0) = range.start().unwrap_or((0, 0));
+    let mut records = 0;
+    let skip_rows = skipped_rows(&range, opt);
+    let cells = range
+        .used_cells()
+        .filter(|(i, j, _)| *i >= skip_rows && *j >= opt.skip_cols);
+    for (i, j, c) in cells {
+        let row = i + row0 as usize;
+        let col = j + col0 as usize;
+        let value = render_cell_at(row as u32, col as u32, j - opt.skip_cols, c, opt)?;
+        let (row, col) = if opt.long_a1 {
+            ((row + 1).to_string(), column_name(col))
+        } else {
+            (row.to_string(), col.to_string())
+        };
+        if let Some(sheet) = sheet_column {
+            write_row(wtr, [sheet, &row, &col, &value], opt)?;
+        } else {
+            write_row(wtr, [&row, &col, &value], opt)?;
+        }
+        records += 1;
+        progress.set_rows(i + 1);
+    }
+    wtr.flush()?;
+    Ok(records)
+}
+
+/// Write a two-column `key | value` sheet as a header row of keys and a row of values, see `--kv-to-record`.
+///
+/// Rows with both cells empty are skipped, returns the number of rows written (2, or 0 for an empty sheet).
+fn worksheet_kv_to_record<W: std::io::Write>(
+    workbook: &mut Workbook,
+    sheet: &str,
+    wtr: &mut csv::Writer<W>,
+    opt: &Opt,
+    progress: &Progress,
+) -> csv::Result<usize> {
+    progress.start_sheet(sheet);
+    let range = workbook.range(sheet)?;
+    let invalid =
+        |msg: String| csv::Error::from(std::io::Error::new(std::io::ErrorKind::InvalidData, msg));
+    let rows: Vec<(usize, &[Data])> = indexed_data_rows(&range, opt).collect();
+    let width = range.width().saturating_sub(opt.skip_cols);
+    let cols = match &opt.kv_cols {
+        // checked to be 2 columns in main
+        Some(ColumnList(cols)) if width > 0 => match cols.iter().find(|&&j| j >= width) {
+            Some(j) => {
+                return Err(invalid(format!(
+                    "--kv-cols column {} is out of the sheet width {}",
+                    j, width
+                )))
+            }
+            None => cols.clone(),
+        },
+        Some(_) => return Ok(0),
+        None => {
+            let used: Vec<usize> = (0..width)
+                .filter(|&j| rows.iter().any(|(_, row)| !matches!(row[j], Data::Empty)))
+                .collect();
+            match used.len() {
+                0 => return Ok(0),
+                2 => used,
+                n => {
+                    return Err(invalid(format!(
+                        "--kv-to-record expects 2 non-empty columns, found {}, specify them by --kv-cols",
+                        n
+                    )))
+                }
+            }
+        }
+    };
+    let (key_col, value_col) = (cols[0], cols[1]);
+    let start = data_start(&range, opt);
+    let naming = opt.empty_header_name.clone().unwrap_or_default();
+    let mut keys = Vec::new();
+    let mut values = Vec::new();
+    for (r, row) in &rows {
+        let key = row.get(key_col).unwrap_or(&Data::Empty);
+        let value = row.get(value_col).unwrap_or(&Data::Empty);
+        if matches!((key, value), (Data::Empty, Data::Empty)) {
+            continue;
+        }
+        let row = start.0 + *r as u32;
+        let key = match render_cell_at(row, start.1 + key_col as u32, key_col, key, opt)?.trim() {
+            "" => naming.render(keys.len(), keys.len()),
+            key => key.to_string(),
+        };
+        keys.push(key);
+        values.push(render_cell_at(
+            row,
+            start.1 + value_col as u32,
+            value_col,
+            value,
+            opt,
+        )?);
+    }
+    if keys.is_empty() {
+        return Ok(0);
+    }
+    let keys = header::dedup_names(keys, &mut std::collections::HashSet::new());
+    write_row(wtr, &keys, opt)?;
+    write_row(wtr, &values, opt)?;
+    progress.set_rows(rows.last().map_or(0, |(r, _)| r + 1));
+    wtr.flush()?;
+    Ok(2)
+}
+
+/// Index of a sheet, `sheet` is expected to be borrowed from `sheetnames`.
+///
+/// Compared by address first, so repeated names still get their own index.
+fn sheet_index(sheetnames: &[String], sheet: &String) -> usize {
+    sheetnames
+        .iter()
+        .position(|s| std::ptr::eq(s, sheet))
+        .or_else(|| sheetnames.iter().position(|s| s == sheet))
+        .expect("sheet in sheetnames")
+}
+
+/// Name of a sheet used in outputs, blank names (of malformed workbooks) are replaced with `sheet_<id>`.
+fn output_name<'a>(sheetnames: &[String], sheet: &'a String) -> Cow<'a, str> {
+    if sheet.trim().is_empty() {
+        Cow::Owned(format!("sheet_{}", sheet_index(sheetnames, sheet)))
+    } else {
+        Cow::Borrowed(sheet)
+    }
+}
+
+/// CSV writer builder with the output options, writers should be built from it.
+///
+/// Errors if the delimiter isn't ASCII, `check_csv_options` reports it as a usage error before.
+fn csv_writer(opt: &Opt) -> std::io::Result<csv::WriterBuilder> {
+    let delimiter = opt.delimiter.to_ascii().map_err(std::io::Error::other)?;
+    let mut builder = csv::WriterBuilder::new();
+    builder
+        .delimiter(delimiter)
+        .quote(opt.quote_char as u8)
+        .quote_style(opt.quote_style.into());
+    if opt.quote_cols.is_some() {
+        // fields are quoted by write_row column by column
+        builder.quote_style(csv::QuoteStyle::Never);
+    }
+    if opt.crlf {
+        builder.terminator(csv::Terminator::CRLF);
+    }
+    if opt.trim != Trim::None {
+        // trimmed records have different lengths
+        builder.flexible(true);
+    }
+    Ok(builder)
+}
+
+/// Quote a field by `quote` if forced or if necessary, the same way as the default csv quoting.
+fn quote_field(field: &[u8], delimiter: u8, quote: u8, force: bool) -> Cow<'_, [u8]> {
+    let necessary = field
+        .iter()
+        .any(|&b| b == delimiter || b == quote || b == b'\n' || b == b'\r');
+    if !force && !necessary {
+        return Cow::Borrowed(field);
+    }
+    let mut quoted = Vec::with_capacity(field.len() + 2);
+    quoted.push(quote);
+    for &b in field {
+        if b == quote {
+            quoted.push(quote);
+        }
+        quoted.push(b);
+    }
+    quoted.push(quote);
+    Cow::Owned(quoted)
+}
+
+/// Write a record, with an extra empty field if `--trailing-delimiter` setted.
+///
+/// With `--quote-cols`, fields are quoted here by their columns.
+fn write_row<W, I, T>(wtr: &mut csv::Writer<W>, record: I, opt: &Opt) -> csv::Result<()>
+where
+    W: std::io::Write,
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    if let Some(cols) = &opt.quote_cols {
+        let delimiter = opt.delimiter.to_ascii().map_err(std::io::Error::other)?;
+        let quote = opt.quote_char as u8;
+        let fields: Vec<Vec<u8>> = record
+            .into_iter()
+            .enumerate()
+            .map(|(col, field)| {
+                quote_field(field.as_ref(), delimiter, quote, cols.contains(col)).into_owned()
+            })
+            .collect();
+        if opt.trailing_delimiter {
+            for field in &fields {
+                wtr.write_field(field)?;
+            }
+            wtr.write_field("")?;
+            return wtr.write_record(None::<&[u8]>);
+        }
+        if fields.len() == 1 && fields[0].is_empty() {
+            // a single empty field is quoted to not be an empty line
+            return wtr.write_record([[quote, quote]]);
+        }
+        return wtr.write_record(&fields);
+    }
+    if opt.trailing_delimiter {
+        for field in record {
+            wtr.write_field(field)?;
+        }
+        wtr.write_field("")?;
+        // an empty record just terminates the fields written above
+        wtr.write_record(None::<&[u8]>)
+    } else {
+        wtr.write_record(record)
+    }
+}
+
+/// Write the `--sheet-banner` line of a sheet, a single field without `--trailing-delimiter`.
+fn write_banner<W: std::io::Write>(wtr: &mut csv::Writer<W>, sheet: &str) -> csv::Result<()> {
+    wtr.write_record([format!("# sheet: {}", sheet)])
+}
+
+/// Write a sheet in the format selected by `--long` or not.
+///
+/// Returns the number of rows (or long format records) written, headers added by `--long` and paging are not counted.
+fn write_sheet<W: std::io::Write>(
+    workbook: &mut Workbook,
+    sheet: &str,
+    wtr: &mut csv::Writer<RawLines<W>>,
+    opt: &Opt,
+    paging: Option<&Paging>,
+    progress: &Progress,
+) -> csv::Result<usize> {
+    if opt.long {
+        write_row(wtr, ["row", "col", "value"], opt)?;
+        worksheet_to_long(workbook, sheet, wtr, opt, None, progress)
+    } else if opt.kv_to_record {
+        worksheet_kv_to_record(workbook, sheet, wtr, opt, progress)
+    } else {
+        worksheet_to_csv(workbook, sheet, wtr, opt, paging, progress, true)
+    }
+}
+
+/// Write a sheet by `write_sheet` to the file created at `path`, gzip-compressed if it ends with `.gz`.
+fn write_sheet_file(
+    workbook: &mut Workbook,
+    sheet: &str,
+    file: File,
+    path: &Path,
+    opt: &Opt,
+    limit: &ByteLimit,
+    progress: &Progress,
+) -> csv::Result<usize> {
+    let file = Encoded::new(OutputFile::new(file, path), opt.encoding, opt.bom)?;
+    let mut wtr = csv_writer(opt)?.from_writer(RawLines::new(limit.wrap(file)));
+    write_meta_header(&mut wtr, workbook, sheet, opt)?;
+    let rows = write_sheet(workbook, sheet, &mut wtr, opt, None, progress)?;
+    let file = wtr.into_inner().map_err(|e| e.into_error())?;
+    file.into_inner().into_inner().into_inner().finish()?;
+    Ok(rows)
+}
+
+/// Extension of `-u` output filenames, by `--extension` or the delimiter.
+fn file_extension(opt: &Opt) -> String {
+    match &opt.extension {
+        Some(ext) => ext.trim_start_matches('.').to_string(),
+        None => opt.delimiter.to_file_extension(),
+    }
+}
+
+/// Number of threads to write `-u` files with, 1 if sheets must be written one by one, see `--jobs`.
+fn write_jobs(opt: &Opt) -> usize {
+    if opt.zip.is_some() || shows_progress(opt) || opt.xlsx == Path::new("-") {
+        return 1;
+    }
+    match opt.jobs {
+        Some(jobs) => jobs.get(),
+        None => std::thread::available_parallelism().map_or(1, NonZeroUsize::get),
+    }
+}
+
+/// Write sheets to their files by `write_sheet_file` with `jobs` threads, results are in the order of `outputs`.
+///
+/// Ranges can't be parsed concurrently from one reader, so each thread opens the workbook on its own.
+fn write_sheet_files_parallel(
+    outputs: &[(&String, PathBuf)],
+    jobs: usize,
+    opt: &Opt,
+    limit: &ByteLimit,
+) -> Vec<Result<csv::Result<usize>, String>> {
+    let next = AtomicUsize::new(0);
+    let write = |workbook: &mut Option<Workbook>, sheet: &str, output: &Path| {
+        let workbook = match workbook {
+            Some(workbook) => workbook,
+            None => workbook.insert(open_workbook(opt)?),
+        };
+        println!("{}", output.display());
+        let file = create_output(output)?;
+        let progress = Progress::disabled();
+        Ok(write_sheet_file(
+            workbook, sheet, file, output, opt, limit, &progress,
+        ))
+    };
+    let mut results: Vec<(usize, Result<csv::Result<usize>, String>)> =
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..jobs.min(outputs.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut workbook = None;
+                        let mut results = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::SeqCst);
+                            let (sheet, output) = match outputs.get(i) {
+                                Some(job) => job,
+                                None => return results,
+                            };
+                            results.push((i, write(&mut workbook, sheet, output)));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("writer thread"))
+                .collect()
+        });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Arguments of a command line as written by `--with-meta-header`, invalid UTF-8 is replaced.
+fn command_line(args: &[OsString]) -> Vec<String> {
+    args.iter()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Write the `--with-meta-header` block of a sheet if set, and not `--raw`.
+fn write_meta_header<W: std::io::Write>(
+    wtr: &mut csv::Writer<RawLines<W>>,
+    workbook: &mut Workbook,
+    sheet: &str,
+    opt: &Opt,
+) -> csv::Result<()> {
+    if !opt.with_meta_header || opt.raw {
+        return Ok(());
+    }
+    let (rows, cols) = workbook.range(sheet)?.get_size();
+    let converted = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .and_then(|t| chrono::DateTime::from_timestamp(t.as_secs() as i64, 0))
+        .map_or_else(String::new, |t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string());
+    let lines = [
+        ("source", opt.xlsx.display().to_string()),
+        ("sheet", sheet.to_string()),
+        ("size", format!("{} rows x {} cols", rows, cols)),
+        ("converted", converted),
+        ("args", opt.args.join(" ")),
+    ];
+    // queued lines go before the next bytes, after the records written so far
+    wtr.flush()?;
+    let terminator = if opt.crlf { "\r\n" } else { "\n" };
+    for (key, value) in lines {
+        let value = value.replace(['\r', '\n'], " ");
+        wtr.get_ref()
+            .push_line(&format!("# {}: {}", key, value), terminator);
+    }
+    Ok(())
+}
+
+/// Hash of the csv output of a sheet by `write_sheet`, without writing it.
+fn content_hash(workbook: &mut Workbook, sheet: &str, opt: &Opt) -> csv::Result<u64> {
+    let mut wtr = csv_writer(opt)?.from_writer(RawLines::new(digest::HashWriter::default()));
+    write_sheet(workbook, sheet, &mut wtr, opt, None, &Progress::disabled())?;
+    wtr.flush()?;
+    let hasher = wtr.into_inner().map_err(|e| e.into_error())?;
+    Ok(hasher.into_inner().finish())
+}
+
+/// Write a sheet as csv by the row options, the header row is left out if `with_header` is false.
+fn worksheet_to_csv<W: std::io::Write>(
+    workbook: &mut Workbook,
+    sheet: &str,
+    wtr: &mut csv::Writer<RawLines<W>>,
+    opt: &Opt,
+    paging: Option<&Paging>,
+    progress: &Progress,
+    with_header: bool,
+) -> csv::Result<usize> {
+    progress.start_sheet(sheet);
+    let range = workbook.range(sheet)?;
+    let size = range.get_size();
+    if size.0 <= skipped_rows(&range, opt) || size.1 <= opt.skip_cols {
+        //panic!("Worksheet range sizes should not be 0, continue");
+        return Ok(0);
+    }
+    let start = data_start(&range, opt);
+    let mut rows = indexed_data_rows(&range, opt);
+    // the header block is rendered up front for the header options,
+    // rows below are rendered into a reused buffer one by one
+    let head_rows = header_rows(opt);
+    let mut head: Vec<(usize, Vec<String>)> = rows
+        .by_ref()
+        .take(head_rows)
+        .map(|(r, row)| {
+            let mut cols = Vec::new();
+            render_row_into(&mut cols, start.0 + r as u32, start.1, row, opt, true)?;
+            Ok((r, cols))
+        })
+        .collect::<std::io::Result<_>>()?;
+    if let Some(n) = opt.merge_header_rows {
+        let block: Vec<(usize, Vec<String>)> = head.drain(..n.get().min(head.len())).collect();
+        let first = block.first().map_or(0, |(r, _)| *r);
+        let block: Vec<Vec<String>> = block.into_iter().map(|(_, cols)| cols).collect();
+        head.insert(0, (first, merge_header_rows(&block, &opt.header_join)));
+    }
+    if let (Some(mode), true) = (opt.units_row, head.len() >= 2) {
+        let (_, units) = head.remove(1);
+        let header = &mut head[0].1;
+        match mode {
+            UnitsRow::Merge => merge_units(header, &units, &opt.units_join),
+            UnitsRow::Drop if opt.units_keep => {
+                // a metadata line before the header, commented out for most readers,
+                // units are trimmed as they are merged
+                let mut line: Vec<String> = units.iter().map(|u| u.trim().to_string()).collect();
+                if let Some(first) = line.first_mut() {
+                    first.insert(0, '#');
+                }
+                write_row(wtr, &line, opt)?;
+            }
+            UnitsRow::Drop => {}
+        }
+    }
+    let mut head = head.into_iter();
+    let (row0, col0) = (start.0 as u64, start.1 as usize);
+    let mut header: Option<Vec<String>> = None;
+    let mut width = None;
+    let mut mapping = Vec::new();
+    let mut written = 0;
+    let mut scientific: Option<(usize, String, String)> = None;
+    let mut cols = Vec::new();
+    for i in 0.. {
+        let r = match head.next() {
+            Some((r, row)) => {
+                cols = row;
+                r
+            }
+            None => match rows.next() {
+                Some((r, row)) => {
+                    render_row_into(&mut cols, start.0 + r as u32, start.1, row, opt, false)?;
+                    r
+                }
+                None => break,
+            },
+        };
+        opt.trim.apply(&mut cols);
+        if opt.warn_scientific {
+            for (j, value) in cols.iter().enumerate() {
+                if is_scientific(value) {
+                    let (count, ..) = scientific.get_or_insert_with(|| {
+                        let cell = format!("{}{}", column_name(col0 + j), row0 + r as u64 + 1);
+                        (0, value.clone(), cell)
+                    });
+                    *count += 1;
+                }
+            }
+        }
+        if i == 0 && opt.width_from_header {
+            // the width is unchanged for a header without any non-empty cell
+            width = cols
+                .iter()
+                .rposition(|name| !name.trim().is_empty())
+                .map(|j| j + 1);
+        }
+        if let Some(width) = width {
+            cols.resize(width, opt.na_rep.clone());
+        }
+        if let (0, Some(prefix)) = (i, &opt.strip_header_prefix) {
+            for name in cols.iter_mut() {
+                if let Some(stripped) = name.strip_prefix(prefix.as_str()) {
+                    *name = stripped.to_string();
+                }
+            }
+        }
+        if let (0, Some(naming)) = (i, &opt.empty_header_name) {
+            for (j, name) in cols.iter_mut().enumerate() {
+                if name.trim().is_empty() {
+                    *name = naming.render(j, col0 + j);
+                }
+            }
+        }
+        if let Some(order) = &opt.column_order_file {
+            if i == 0 {
+                mapping = order
+                    .mapping(&cols, opt.drop_extra_columns, opt.strict_schema)
+                    .map_err(|msg| {
+                        let msg = format!("--column-order-file: {}", msg);
+                        std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+                    })?;
+                cols = order.apply_header(&cols, &mapping);
+            } else {
+                cols = order.apply(&cols, &mapping, &opt.na_rep);
+            }
+        }
+        if let Some(columns) = &opt.columns {
+            if i == 0 {
+                mapping = columns
+                    .mapping(&cols, true, false)
+                    .expect("mapping without strict");
+                let missing = columns.missing(&mapping);
+                if !missing.is_empty() {
+                    let msg = format!(
+                        "--columns: {} not in the header of sheet `{}`, available: {}",
+                        missing.join(", "),
+                        sheet,
+                        cols.join(", ")
+                    );
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, msg).into());
+                }
+            }
+            cols = columns.apply(&cols, &mapping, &opt.na_rep);
+        }
+        if opt.with_row_number {
+            let number = match (opt.row_number_basis, i) {
+                (RowNumberBasis::Worksheet, _) => {
+                    (opt.row_number_start + row0 + r as u64).to_string()
+                }
+                (RowNumberBasis::Data, 0) => "row".to_string(),
+                (RowNumberBasis::Data, i) => (opt.row_number_start + i as u64 - 1).to_string(),
+            };
+            cols.insert(0, number);
+        }
+        if opt.with_sheet_name {
+            let name = match (i, &opt.sheet_name_header) {
+                (0, Some(header)) => header,
+                (0, None) if opt.concat => "__sheet__",
+                (0, None) => "sheet",
+                _ => sheet,
+            };
+            cols.insert(0, name.to_string());
+        }
+        if opt.with_source_file {
+            let file = match i {
+                0 => opt.source_file_header.clone(),
+                _ => opt.xlsx.display().to_string(),
+            };
+            cols.insert(0, file);
+        }
+        if let Some(paging) = paging {
+            // pages are counted by data rows, the header row is 0
+            if i > 1 && (i - 1) % paging.rows == 0 {
+                // a raw line, so the separator isn't quoted as a csv field
+                wtr.flush()?;
+                let terminator = if opt.crlf { "\r\n" } else { "\n" };
+                wtr.get_ref().push_line(&paging.separator, terminator);
+                if let Some(header) = &header {
+                    write_row(wtr, header, opt)?;
+                }
+            }
+            if i == 0 && paging.repeat_header {
+                header = Some(cols.clone());
+            }
+        }
+        if i == 0 && !with_header {
+            continue;
+        }
+        write_row(wtr, &cols, opt)?;
+        written += 1;
+        progress.set_rows(r + 1);
+    }
+    if let Some((count, value, cell)) = scientific {
+        eprintln!(
+            "warning: sheet `{}` has {} values in e notation, like `{}` at {}, digits of long numbers may be lost in the source",
+            sheet, count, value, cell
+        );
+    }
+    wtr.flush()?;
+    Ok(written)
+}
+
+/// Whether a value is a number written in scientific notation, like `1.23E+11`.
+fn is_scientific(value: &str) -> bool {
+    let value = value.trim();
+    value.contains(['e', 'E']) && value.parse::<f64>().is_ok_and(f64::is_finite)
+}
+
+/// Merge a multi-row header block into a single header row, see `--merge-header-rows`.
+///
+/// Non-empty cells of each column are joined with `sep`, empty cells are skipped.
+fn merge_header_rows(block: &[Vec<String>], sep: &str) -> Vec<String> {
+    let width = block.iter().map(Vec::len).max().unwrap_or(0);
+    (0..width)
+        .map(|col| {
+            block
+                .iter()
+                .filter_map(|row| row.get(col))
+                .filter(|cell| !cell.trim().is_empty())
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(sep)
+        })
+        .collect()
+}
+
+/// Fold units into header names as `name (unit)`, with `join` between, see `--units-row merge`.
+///
+/// Names of empty units are unchanged.
+fn merge_units(header: &mut [String], units: &[String], join: &str) {
+    for (name, unit) in header.iter_mut().zip(units) {
+        if !unit.trim().is_empty() {
+            *name = format!("{}{}({})", name, join, unit.trim());
+        }
+    }
+}
+
+/// Split an output arg like `Sheet3=out.csv` or `2=out.csv` into the sheet and the path, at the first `=`
+/// where the part before is a sheet name or id, `None` for a plain path.
+fn mapped_output<'a>(
+    output: &Path,
+    sheetnames: &'a [String],
+    opt: &Opt,
+) -> Option<(&'a String, PathBuf)> {
+    let arg = output.to_str()?;
+    arg.match_indices('=').find_map(|(i, _)| {
+        let (selector, path) = (&arg[..i], &arg[i + 1..]);
+        if path.is_empty() {
+            return None;
+        }
+        let sheet = match sheetnames.iter().find(|s| *s == selector) {
+            Some(sheet) => sheet,
+            None => selector
+                .parse::<SheetSelector>()
+                .ok()?
+                .find_in(sheetnames, opt.name_match())
+                .ok()?,
+        };
+        Some((sheet, PathBuf::from(path)))
+    })
+}
+
+/// Pair output args to sheets, in the order of the args.
+///
+/// Args like `Sheet3=out.csv` go to their sheets, plain paths to the other sheets in order, the selected
+/// ones with `--select` or all of the workbook. Plain paths or selected sheets left unpaired are warned.
+fn pair_outputs<'a>(
+    sheetnames: &'a [String],
+    opt: &Opt,
+    summary: &mut Summary,
+) -> Result<Vec<(&'a String, PathBuf)>, ExitError> {
+    let mapped: Vec<Option<(&String, PathBuf)>> = opt
+        .output
+        .iter()
+        .map(|output| mapped_output(output, sheetnames, opt))
+        .collect();
+    let mapped_sheets: Vec<&String> = mapped.iter().flatten().map(|(sheet, _)| *sheet).collect();
+    let candidates = match &opt.select {
+        Some(select) => select
+            .find_in(sheetnames, opt.name_match())
+            .map_err(usage_error)?,
+        None => sheetnames.iter().collect(),
+    };
+    let mut rest = candidates
+        .into_iter()
+        .filter(|sheet| !mapped_sheets.contains(sheet));
+    let mut outputs = Vec::new();
+    let mut unpaired = Vec::new();
+    for (output, mapped) in opt.output.iter().zip(mapped) {
+        match mapped.or_else(|| rest.next().map(|sheet| (sheet, output.clone()))) {
+            Some(pair) => outputs.push(pair),
+            None => unpaired.push(output.display().to_string()),
+        }
+    }
+    if !unpaired.is_empty() {
+        eprintln!(
+            "warning: no sheet left for {} output(s), not written: {}",
+            unpaired.len(),
+            unpaired.join(", ")
+        );
+    }
+    // without `--select`, other sheets than those of `SHEET=PATH` outputs are left out on purpose
+    let left: Vec<&String> = rest.collect();
+    if !left.is_empty() && (opt.select.is_some() || mapped_sheets.is_empty()) {
+        eprintln!(
+            "warning: {} sheet(s) without an output, not written: {} (use `SHEET=PATH` outputs or -u)",
+            left.len(),
+            left.iter()
+                .map(|s| format!("`{}`", s))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        for sheet in left {
+            summary.add(sheet, Status::Skipped("no output path".to_string()), 0);
+        }
+    }
+    Ok(outputs)
+}
+
+/// Header rows of `--concat`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ConcatHeader {
+    First,
+    All,
+    None,
+}
+
+impl std::str::FromStr for ConcatHeader {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "first" => Ok(ConcatHeader::First),
+            "all" => Ok(ConcatHeader::All),
+            "none" => Ok(ConcatHeader::None),
+            s => Err(format!(
+                "unknown concat header `{}`, supports: first, all, none",
+                s
+            )),
+        }
+    }
+}
+
+/// Format of `--list`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ListFormat {
+    Names,
+    Table,
+    Json,
+}
+
+impl std::str::FromStr for ListFormat {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "names" => Ok(ListFormat::Names),
+            "table" => Ok(ListFormat::Table),
+            "json" => Ok(ListFormat::Json),
+            s => Err(format!(
+                "unknown list format `{}`, supports: names, table, json",
+                s
+            )),
+        }
+    }
+}
+
+/// Print the sheets of `--list`, only `table` and `json` formats parse the sheets for dimensions.
+fn list_sheets(workbook: &mut Workbook, sheetnames: &[String], opt: &Opt) -> std::io::Result<()> {
+    let format = opt.list_format.unwrap_or(ListFormat::Names);
+    if format == ListFormat::Names {
+        for sheet in sheetnames {
+            println!("{}", sheet);
+        }
+        return Ok(());
+    }
+    let mut rows = Vec::new();
+    let mut objects = Vec::new();
+    for (index, sheet) in sheetnames.iter().enumerate() {
+        let (height, width) = workbook.range(sheet)?.get_size();
+        let visibility = match workbook.visibility(sheet) {
+            calamine::SheetVisible::Visible => "visible",
+            calamine::SheetVisible::Hidden => "hidden",
+            calamine::SheetVisible::VeryHidden => "very-hidden",
+        };
+        if format == ListFormat::Json {
+            objects.push(serde_json::json!({
+                "index": index,
+                "name": sheet,
+                "rows": height,
+                "cols": width,
+                "visibility": visibility,
+            }));
+        } else {
+            rows.push([
+                index.to_string(),
+                sheet.clone(),
+                height.to_string(),
+                width.to_string(),
+                visibility.to_string(),
+            ]);
+        }
+    }
+    if format == ListFormat::Json {
+        println!("{}", serde_json::Value::Array(objects));
+    } else {
+        let header = ["index", "name", "rows", "cols", "visibility"];
+        for line in summary::table_lines(&header, &rows, &[0, 2, 3]) {
+            println!("{}", line);
+        }
+    }
+    Ok(())
+}
+
+/// Output filename template with placeholders, see `--name-template`.
+struct NameTemplate {
+    template: String,
+    placeholder: Regex,
+    regex: Option<Regex>,
+    require_match: bool,
+}
+
+impl NameTemplate {
+    fn new(opt: &Opt) -> Result<Self, String> {
+        let template = opt
+            .name_template
+            .clone()
+            .unwrap_or_else(|| "{name}.{ext}".to_string());
+        let regex = match &opt.name_from_regex {
+            Some(re) => Some(Regex::new(re).map_err(|e| e.to_string())?),
+            None => None,
+        };
+        let placeholder = Regex::new(r"\{([^{}]+)\}").unwrap();
+        for caps in placeholder.captures_iter(&template) {
+            let key = &caps[1];
+            let valid = match key {
+                "name" | "index" | "ext" => true,
+                _ => regex.as_ref().is_some_and(|re| match key.parse::<usize>() {
+                    Ok(n) => n < re.captures_len(),
+                    Err(_) => re.capture_names().any(|name| name == Some(key)),
+                }),
+            };
+            if !valid {
+                return Err(format!(
+                    "unknown placeholder `{{{}}}` in name template",
+                    key
+                ));
+            }
+        }
+        Ok(NameTemplate {
+            template,
+            placeholder,
+            regex,
+            require_match: opt.require_name_match,
+        })
+    }
+
+    /// Render the filename of a sheet, `None` if it should be skipped.
+    ///
+    /// The regex is matched against `sheet`, while `{name}` is replaced with `name`.
+    /// Values of placeholders are sanitized, a name sanitized to empty is `sheet_<index>`, and a filename
+    /// already in `used` gets `{name}` (or the filename before `.{ext}` if there's no `{name}`)
+    /// suffixed with `_<index>`.
+    fn render(
+        &self,
+        sheet: &str,
+        name: &str,
+        index: usize,
+        ext: &str,
+        used: &mut HashSet<String>,
+    ) -> Option<String> {
+        let (template, caps) = match &self.regex {
+            Some(re) => match re.captures(sheet) {
+                Some(caps) => (self.template.as_str(), Some(caps)),
+                None if self.require_match => return None,
+                None => ("{name}.{ext}", None),
+            },
+            None => (self.template.as_str(), None),
+        };
+        let name = match sanitize_filename(name) {
+            name if name.is_empty() => format!("sheet_{}", index),
+            name => name,
+        };
+        let mut filename = self.fill(template, caps.as_ref(), &name, index, ext);
+        // sanitized names may collide, like `a/b` and `a:b`
+        if used.contains(&filename) {
+            let name = format!("{}_{}", name, index);
+            filename = self.fill(template, caps.as_ref(), &name, index, ext);
+        }
+        if used.contains(&filename) {
+            let base = filename.rfind('/').map_or(0, |i| i + 1);
+            let at = filename[base..]
+                .rfind(&format!(".{}", ext))
+                .map_or(filename.len(), |i| base + i);
+            filename.insert_str(at, &format!("_{}", index));
+        }
+        used.insert(filename.clone());
+        Some(filename)
+    }
+
+    fn fill(
+        &self,
+        template: &str,
+        caps: Option<&regex::Captures>,
+        name: &str,
+        index: usize,
+        ext: &str,
+    ) -> String {
+        self.placeholder
+            .replace_all(template, |c: &regex::Captures| {
+                let key = &c[1];
+                match key {
+                    "name" => name.to_string(),
+                    "index" => index.to_string(),
+                    "ext" => ext.to_string(),
+                    _ => {
+                        let caps = caps.expect("captures for placeholder");
+                        let group = match key.parse::<usize>() {
+                            Ok(n) => caps.get(n),
+                            Err(_) => caps.name(key),
+                        };
+                        sanitize_filename(group.map(|m| m.as_str()).unwrap_or(""))
+                    }
+                }
+            })
+            .into_owned()
+    }
+}
+
+/// Replace characters illegal in filenames (`/ \ : * ? " < > |` and controls) with `_`,
+/// and names of only dots like `..`, so a value can't escape its directory.
+fn sanitize_filename(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    if name.chars().all(|c| c == '.') {
+        "_".repeat(name.len())
+    } else {
+        name
+    }
+}
+
+/// Whether an existing output file should be overwritten, see `--yes` and `--no-clobber`.
+fn confirm_overwrite(output: &Path, opt: &Opt) -> bool {
+    if opt.yes {
+        return true;
+    }
+    if opt.no_clobber {
+        return false;
+    }
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return true;
+    }
+    eprint!("overwrite {}? [y/N] ", output.display());
+    let mut answer = String::new();
+    if stdin.read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Print the files `-u` would write with `--dry-run`, and the sheets skipped with the reasons.
+///
+/// `sheets` are the filtered ones, `--dedup-sheets` is not checked as it reads the cells.
+fn dry_run_sheet_names(
+    sheetnames: &[String],
+    sheets: &[&String],
+    template: &NameTemplate,
+    input_stem: Option<&str>,
+    opt: &Opt,
+) {
+    let ext = file_extension(opt);
+    let workdir = opt.workdir.clone().unwrap_or_default();
+    if let (Some(dir), None) = (&opt.workdir, &opt.zip) {
+        match std::fs::metadata(dir) {
+            Ok(meta) if !meta.is_dir() => {
+                eprintln!("warning: --workdir {} is not a directory", dir.display())
+            }
+            Ok(meta) if meta.permissions().readonly() => {
+                eprintln!("warning: --workdir {} is not writable", dir.display())
+            }
+            Ok(_) => {}
+            Err(_) => eprintln!(
+                "warning: --workdir {} does not exist, it would be created",
+                dir.display()
+            ),
+        }
+    }
+    if opt.dedup_sheets {
+        eprintln!("warning: --dedup-sheets compares the cells of sheets, not checked in a dry run");
+    }
+    let mut used = HashSet::new();
+    for sheet in sheetnames {
+        if !sheets.contains(&sheet) {
+            println!("skip `{}`: filtered out by --include/--exclude", sheet);
+            continue;
+        }
+        let index = sheet_index(sheetnames, sheet);
+        let sheet_name = output_name(sheetnames, sheet);
+        let name = input_stem.unwrap_or(&sheet_name);
+        let output = match template.render(&sheet_name, name, index, &ext, &mut used) {
+            Some(name) => PathBuf::from(name),
+            None => {
+                println!("skip `{}`: not matching --name-from-regex", sheet);
+                continue;
+            }
+        };
+        match &opt.zip {
+            Some(zip) => println!(
+                "write `{}` to {} in {}",
+                sheet,
+                zip_entry_name(&output),
+                zip.display()
+            ),
+            None => {
+                let output = workdir.join(output);
+                if !output.exists() {
+                    println!("write `{}` to {}", sheet, output.display());
+                } else if opt.no_clobber {
+                    println!("skip `{}`: {} exists", sheet, output.display());
+                } else {
+                    println!("write `{}` to {} (exists)", sheet, output.display());
+                }
+            }
+        }
+    }
+}
+
+/// Name of a ZIP archive entry for an output filename, with `/` separators and without
+/// absolute, `.` or `..` components that could escape the extraction directory.
+fn zip_entry_name(output: &Path) -> String {
+    output
+        .to_string_lossy()
+        .split(['/', '\\'])
+        .filter(|part| !matches!(*part, "" | "." | ".."))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Error if writing `sheet` to `output` (stdout if `None`) failed, the partial file is removed.
+///
+/// With `--summary`, the failed sheet is recorded and the summary is printed before the error is returned.
+fn check_write<T>(
+    result: csv::Result<T>,
+    sheet: &str,
+    output: Option<&Path>,
+    summary: &mut Summary,
+) -> Result<T, String> {
+    let err = match result {
+        Ok(value) => return Ok(value),
+        Err(err) => err,
+    };
+    if let Some(path) = output {
+        let _ = std::fs::remove_file(path);
+    }
+    summary.add(sheet, Status::Error(err.to_string()), 0);
+    summary.finish()?;
+    Err(format!(
+        "write sheet `{}` to {}: {}",
+        sheet,
+        display_output(output),
+        err
+    ))
+}
+
+/// Create an output file, with the path in the error message.
+fn create_output(path: &Path) -> Result<File, String> {
+    File::create(path).map_err(|e| format!("create {}: {}", path.display(), e))
+}
+
+/// Name of an output in messages, the path or `stdout` if `None`.
+fn display_output(output: Option<&Path>) -> String {
+    output.map_or_else(|| "stdout".to_string(), |path| path.display().to_string())
+}
+
+/// Write the selected (or first) sheet as a record batch to the only output path by `write`,
+/// for columnar formats named `format` in messages.
+#[cfg(any(feature = "parquet", feature = "arrow"))]
+fn write_batch_sheet(
+    format: &str,
+    workbook: &mut Workbook,
+    sheetnames: &[String],
+    opt: &Opt,
+    summary: &mut Summary,
+    write: impl Fn(&Path, &arrow_array::RecordBatch) -> Result<(), String>,
+) -> Result<(), Box<dyn Error>> {
+    if opt.output.len() != 1 || opt.use_sheet_names {
+        let msg = format!("{} format requires exactly one output file path", format);
+        return Err(usage_error(msg).into());
+    }
+    let sheet = match &opt.select {
+        Some(select) => match select.find_in(sheetnames, opt.name_match()) {
+            Ok(sheets) if sheets.len() == 1 => Ok(sheets[0]),
+            Ok(_) => Err(format!("{} format writes a single sheet", format)),
+            Err(msg) => Err(msg),
+        }
+        .map_err(usage_error)?,
+        None => &sheetnames[0],
+    };
+    // the failed sheet is recorded in the summary, printed before the error is returned
+    let fail = |summary: &mut Summary, status: String, msg: String| {
+        summary.add(sheet, Status::Error(status), 0);
+        summary.finish()?;
+        Err(msg.into())
+    };
+    let range = match workbook.range(sheet) {
+        Ok(range) => range,
+        Err(e) => return fail(summary, e.to_string(), e.to_string()),
+    };
+    let batch = match columnar::sheet_to_batch(&range, opt) {
+        Ok(Some(batch)) => batch,
+        Ok(None) => {
+            let msg = format!("sheet `{}` is empty, nothing to write", sheet);
+            return fail(summary, "empty sheet".to_string(), msg);
+        }
+        Err(e) => return fail(summary, e.to_string(), format!("sheet `{}`: {}", sheet, e)),
+    };
+    let output = &opt.output[0];
+    if let Err(e) = write(output, &batch) {
+        let _ = std::fs::remove_file(output);
+        let msg = format!("write sheet `{}` to {}: {}", sheet, output.display(), e);
+        return fail(summary, e, msg);
+    }
+    println!("{}", output.display());
+    summary.written(sheet, Some(output), batch.num_rows());
+    summary.finish()?;
+    Ok(())
+}
+
+/// Sheets selected by `-s`, or all sheets filtered by `-I` and `-X` otherwise.
+fn selected_or_filtered<'a>(
+    sheetnames: &'a [String],
+    opt: &Opt,
+) -> Result<Vec<&'a String>, ExitError> {
+    match &opt.select {
+        Some(select) => select
+            .find_in(sheetnames, opt.name_match())
+            .map_err(usage_error),
+        None => filter_sheets(sheetnames, opt),
+    }
+}
+
+/// Usage error if a document `format` has more than one output path, or `-u`.
+fn check_single_output(format: &str, opt: &Opt) -> Result<(), ExitError> {
+    if opt.output.len() > 1 || opt.use_sheet_names {
+        return Err(usage_error(format!(
+            "{} format writes to stdout or exactly one output file",
+            format
+        )));
+    }
+    Ok(())
+}
+
+/// Write sheets to stdout or the only output path, as a single document made by `new` on the writer.
+fn write_document<S, F>(
+    new: F,
+    sheets: Vec<&String>,
+    workbook: &mut Workbook,
+    sheetnames: &[String],
+    opt: &Opt,
+    limit: &ByteLimit,
+    summary: &mut Summary,
+) -> Result<(), Box<dyn Error>>
+where
+    S: SheetWriter,
+    F: FnOnce(LimitedWriter<Box<dyn std::io::Write>>) -> std::io::Result<S>,
+{
+    let output = opt.output.first().map(PathBuf::as_path);
+    let writer: Box<dyn std::io::Write> = match output {
+        Some(path) => Box::new(create_output(path)?),
+        None => Box::new(std::io::stdout()),
+    };
+    let mut document =
+        new(limit.wrap(writer)).map_err(|e| format!("write {}: {}", display_output(output), e))?;
+    for sheet in sheets {
+        let result = workbook
+            .range(sheet)
+            .and_then(|range| document.write_sheet(&output_name(sheetnames, sheet), &range, opt));
+        let rows = check_write(result.map_err(csv::Error::from), sheet, output, summary)?;
+        summary.written(sheet, output, rows);
+    }
+    document
+        .finish()
+        .map_err(|e| format!("write {}: {}", display_output(output), e))?;
+    summary.finish()?;
+    Ok(())
+}
+
+/// Whether a progress bar is shown, by `--progress-eta`, or `--progress` on a terminal.
+fn shows_progress(opt: &Opt) -> bool {
+    opt.progress_eta || (opt.progress && std::io::stderr().is_terminal())
+}
+
+/// Progress of `--progress-eta` or `--progress` over the sheets to write in order, disabled otherwise.
+fn start_progress(workbook: &mut Workbook, sheets: &[&String], opt: &Opt) -> Progress {
+    if !shows_progress(opt) {
+        return Progress::disabled();
+    }
+    let totals = sheets
+        .iter()
+        // parse errors are reported by the conversion
+        .map(|sheet| (sheet.to_string(), workbook.row_count(sheet).unwrap_or(0)))
+        .collect();
+    if opt.progress {
+        Progress::per_sheet(totals)
+    } else {
+        Progress::new(totals)
+    }
+}
+
+/// Usage error if the delimiter or quote character can't be used for csv output.
+pub(crate) fn check_csv_options(opt: &Opt) -> Result<(), ExitError> {
+    opt.delimiter.to_ascii().map_err(usage_error)?;
+    if !opt.quote_char.is_ascii() {
+        let msg = format!("--quote-char `{}` is not ASCII", opt.quote_char);
+        return Err(usage_error(msg));
+    }
+    Ok(())
+}
+
+/// Exit code of usage errors, like invalid or conflicting arguments.
+const EXIT_USAGE: i32 = 2;
+
+/// Error of `run` with the exit code of the process, other errors exit with 1.
+#[derive(Debug)]
+pub struct ExitError {
+    code: i32,
+    message: String,
+}
+
+impl ExitError {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        ExitError {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Exit code of the process, like 2 for usage errors.
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+}
+
+impl std::fmt::Display for ExitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for ExitError {}
+
+/// Usage error of the message, reported like the argument errors.
+fn usage_error(msg: impl Into<String>) -> ExitError {
+    ExitError::new(EXIT_USAGE, msg)
+}
+
+/// Exit code when `--include` and `--exclude` filter out all sheets.
+const EXIT_ALL_FILTERED: i32 = 6;
+
+/// Filter sheet names with `--include` first and `--exclude` after, invalid patterns are usage errors.
+fn filter_sheets<'a>(sheetnames: &'a [String], opt: &Opt) -> Result<Vec<&'a String>, ExitError> {
+    let build = |p: &String| {
+        RegexBuilder::new(p)
+            .case_insensitive(opt.ignore_case)
+            .build()
+            .map_err(|e| usage_error(e.to_string()))
+    };
+    let include_pattern = opt.include.as_ref().map(build).transpose()?;
+    let exclude_pattern = opt.exclude.as_ref().map(build).transpose()?;
+    let sheets = sheetnames
+        .iter()
+        .filter(|name| {
+            include_pattern
+                .as_ref()
+                .map(|r| r.is_match(name))
+                .unwrap_or(true)
+        })
+        .filter(|name| {
+            exclude_pattern
+                .as_ref()
+                .map(|r| !r.is_match(name))
+                .unwrap_or(true)
+        })
+        .collect();
+    Ok(sheets)
+}
+
+fn open_workbook(opt: &Opt) -> Result<Workbook, String> {
+    Workbook::open(opt).map_err(|e| format!("open {}: {}", opt.xlsx.display(), e))
+}
+
+/// Run the command line of the process arguments, errors are returned for `main` to report.
+///
+/// An `ExitError` tells the exit code of the process, other errors exit with 1.
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let args: Vec<OsString> = std::env::args_os().collect();
+    let mut opt = match Opt::from_iter_safe(&args) {
+        Ok(opt) => opt,
+        Err(e) if e.use_stderr() => {
+            // without the (maybe colored) `error:` of clap, it's added by `main`
+            let msg = e
+                .message
+                .split_once(' ')
+                .map_or(&*e.message, |(_, msg)| msg);
+            return Err(usage_error(msg).into());
+        }
+        // --help and --version
+        Err(e) => {
+            println!("{}", e.message);
+            return Ok(());
+        }
+    };
+    opt.args = command_line(&args[1..]);
+    if opt.format == Format::Tsv {
+        opt.delimiter = Delimiter('\t');
+    }
+    if opt.concat {
+        opt.with_sheet_name = true;
+    }
+    let mut lists = std::mem::take(&mut opt.select_lists);
+    for list in lists.iter_mut().filter(|list| list.is_stdin()) {
+        if opt.xlsx == Path::new("-") {
+            return Err(usage_error(
+                "`--select -` and the workbook `-` can't both read from stdin",
+            )
+            .into());
+        }
+        let stdin = std::io::stdin();
+        match SheetSelection::from_reader(stdin.lock()) {
+            Ok(selection) => *list = selection,
+            Err(msg) => {
+                return Err(usage_error(msg).into());
+            }
+        }
+    }
+    opt.select = SheetSelection::concat(lists);
+    if opt.whitespace_is_empty
+        && !opt.skip_empty_rows
+        && !opt.trim_trailing_rows
+        && opt.trim == Trim::None
+    {
+        return Err(usage_error(
+            "--whitespace-is-empty requires --skip-empty-rows, --trim-trailing-rows or --trim",
+        )
+        .into());
+    }
+    if opt.dry_run
+        && (!matches!(opt.format, Format::Csv | Format::Tsv)
+            || (!opt.use_sheet_names && opt.output.is_empty()))
+    {
+        return Err(usage_error("--dry-run reports csv files of `-u` or output paths").into());
+    }
+    if opt.concat && !matches!(opt.format, Format::Csv | Format::Tsv) {
+        return Err(usage_error(
+            "--concat writes csv, other formats have their own multi-sheet outputs",
+        )
+        .into());
+    }
+    if opt.kv_cols.as_ref().is_some_and(|cols| cols.0.len() != 2) {
+        return Err(usage_error("--kv-cols expects a key and a value column, like `0,1`").into());
+    }
+    if opt.properties {
+        if opt.xlsx == Path::new("-") {
+            return Err(usage_error(
+                "--properties reads the workbook file, not supported from stdin",
+            )
+            .into());
+        }
+        let props = properties::read_properties(&opt.xlsx)
+            .map_err(|e| format!("read properties of {}: {}", opt.xlsx.display(), e))?;
+        match props {
+            Some(props) if opt.json => {
+                let map: serde_json::Map<_, _> = props
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), serde_json::Value::String(v)))
+                    .collect();
+                println!("{}", serde_json::Value::Object(map));
+            }
+            Some(props) => {
+                for (key, value) in props {
+                    println!("{}: {}", key, value);
+                }
+            }
+            None if opt.json => println!("null"),
+            None => eprintln!("workbook properties unavailable for {}", opt.xlsx.display()),
+        }
+        return Ok(());
+    }
+    let mut workbook = open_workbook(&opt)?;
+    if opt.xls_codepage.is_some() && !workbook.is_xls() {
+        eprintln!("warning: --xls-codepage only applies to .xls input, ignored");
+    }
+    let sheetnames = workbook.sheet_names();
+    if sheetnames.is_empty() {
+        let msg = format!("input file {} has zero sheet", opt.xlsx.display());
+        return Err(ExitError::new(opt.empty_workbook_exit_code, msg).into());
+    }
+    let missing: Vec<String> = opt
+        .require_sheet
+        .iter()
+        .filter_map(|selector| selector.find_all_in(&sheetnames, opt.name_match()).err())
+        .collect();
+    if !missing.is_empty() {
+        // an error line per missing sheet, the first one is prefixed by `main`
+        let msgs: Vec<String> = missing
+            .iter()
+            .map(|msg| format!("required {}", msg))
+            .collect();
+        return Err(msgs.join("\nerror: ").into());
+    }
+
+    if opt.list {
+        list_sheets(&mut workbook, &sheetnames, &opt)?;
+        return Ok(());
+    }
+    let filtered = opt.include.is_some() || opt.exclude.is_some();
+    if filtered && opt.select.is_none() && filter_sheets(&sheetnames, &opt)?.is_empty() {
+        let msg = format!(
+            "all {} sheets are filtered out by --include and --exclude",
+            sheetnames.len()
+        );
+        return Err(ExitError::new(EXIT_ALL_FILTERED, msg).into());
+    }
+
+    if opt.dims {
+        let mut dims = Vec::new();
+        for sheet in filter_sheets(&sheetnames, &opt)? {
+            let (rows, cols) = workbook.size(sheet)?;
+            if opt.json {
+                dims.push(serde_json::json!({ "name": sheet, "rows": rows, "cols": cols }));
+            } else {
+                println!("{}\t{}\t{}", sheet, rows, cols);
+            }
+        }
+        if opt.json {
+            println!("{}", serde_json::Value::Array(dims));
+        }
+        return Ok(());
+    }
+
+    if opt.bench_all {
+        check_csv_options(&opt)?;
+        let sheet = match &opt.select {
+            Some(select) => match select.find_in(&sheetnames, opt.name_match()) {
+                Ok(sheets) if sheets.len() == 1 => Ok(sheets[0]),
+                Ok(_) => Err("--bench-all benchmarks a single sheet".to_string()),
+                Err(msg) => Err(msg),
+            },
+            None => Ok(&sheetnames[0]),
+        }
+        .map_err(usage_error)?;
+        bench::bench_all(&mut workbook, sheet, &opt)
+            .map_err(|e| format!("benchmark sheet `{}`: {}", sheet, e))?;
+        return Ok(());
+    }
+
+    if let Some(dialect) = opt.emit_ddl {
+        let sheets = selected_or_filtered(&sheetnames, &opt)?;
+        let inner: Box<dyn std::io::Write> = match &opt.ddl_file {
+            Some(path) => Box::new(create_output(path)?),
+            None => Box::new(std::io::stderr()),
+        };
+        let mut ddl = ddl::DdlWriter::new(inner, dialect);
+        for sheet in sheets {
+            let range = workbook.range(sheet)?;
+            ddl.write_sheet(&output_name(&sheetnames, sheet), &range, &opt)
+                .map_err(|e| format!("write ddl of sheet `{}`: {}", sheet, e))?;
+        }
+        ddl.finish().map_err(|e| format!("write ddl: {}", e))?;
+    }
+
+    let limit = ByteLimit::new(opt.max_total_bytes);
+    let mut summary = Summary::new(opt.summary, opt.index_file.clone());
+    match opt.format {
+        Format::Csv | Format::Tsv => check_csv_options(&opt)?,
+        Format::Html => {
+            check_single_output("html", &opt)?;
+            let sheets = selected_or_filtered(&sheetnames, &opt)?;
+            write_document(
+                html::HtmlWriter::new,
+                sheets,
+                &mut workbook,
+                &sheetnames,
+                &opt,
+                &limit,
+                &mut summary,
+            )?;
+            return Ok(());
+        }
+        Format::JsonWorkbook => {
+            check_single_output("json-workbook", &opt)?;
+            let sheets = selected_or_filtered(&sheetnames, &opt)?;
+            write_document(
+                json::JsonWorkbookWriter::new,
+                sheets,
+                &mut workbook,
+                &sheetnames,
+                &opt,
+                &limit,
+                &mut summary,
+            )?;
+            return Ok(());
+        }
+        Format::Jsonl | Format::Json => {
+            let array = opt.format == Format::Json;
+            check_single_output(if array { "json" } else { "jsonl" }, &opt)?;
+            let sheets = match &opt.select {
+                Some(select) => select
+                    .find_in(&sheetnames, opt.name_match())
+                    .map_err(usage_error)?,
+                None => match opt.default_action {
+                    DefaultAction::First => vec![&sheetnames[0]],
+                    DefaultAction::All => filter_sheets(&sheetnames, &opt)?,
+                    DefaultAction::Error => {
+                        return Err(usage_error(
+                            "no sheet selected, use `-s` (see --default-action)",
+                        )
+                        .into())
+                    }
+                },
+            };
+            write_document(
+                |writer| json::JsonRowsWriter::new(writer, array),
+                sheets,
+                &mut workbook,
+                &sheetnames,
+                &opt,
+                &limit,
+                &mut summary,
+            )?;
+            return Ok(());
+        }
+        #[cfg(feature = "sqlite")]
+        Format::Sqlite => {
+            if opt.output.len() != 1 || opt.use_sheet_names {
+                return Err(
+                    usage_error("sqlite format requires exactly one output database path").into(),
+                );
+            }
+            let mut db = sqlite::SqliteWriter::open(&opt.output[0])
+                .map_err(|e| format!("open database {}: {}", opt.output[0].display(), e))?;
+            for sheet in selected_or_filtered(&sheetnames, &opt)? {
+                let range = workbook.range(sheet)?;
+                let (table, rows) = db
+                    .write_sheet(&output_name(&sheetnames, sheet), &range, &opt)
+                    .map_err(|e| format!("write sheet `{}` to database: {}", sheet, e))?;
+                println!("{}", table);
+                summary.written(sheet, Some(&opt.output[0]), rows);
+            }
+            summary.finish()?;
+            return Ok(());
+        }
+        #[cfg(feature = "msgpack")]
+        Format::Msgpack => {
+            if opt.output.len() > 1 || opt.use_sheet_names {
+                return Err(usage_error(
+                    "msgpack format writes to stdout or exactly one output file",
+                )
+                .into());
+            }
+            let sheet = match &opt.select {
+                Some(select) => match select.find_in(&sheetnames, opt.name_match()) {
+                    Ok(sheets) if sheets.len() == 1 => Ok(sheets[0]),
+                    Ok(_) => Err("msgpack format writes a single sheet".to_string()),
+                    Err(msg) => Err(msg),
+                }
+                .map_err(usage_error)?,
+                None => &sheetnames[0],
+            };
+            let output = opt.output.first().map(PathBuf::as_path);
+            let writer: Box<dyn std::io::Write> = match output {
+                Some(path) => Box::new(create_output(path)?),
+                None => Box::new(std::io::stdout()),
+            };
+            let result = workbook
+                .range(sheet)
+                .and_then(|range| msgpack::write_sheet(limit.wrap(writer), &range, &opt));
+            let rows = check_write(
+                result.map_err(csv::Error::from),
+                sheet,
+                output,
+                &mut summary,
+            )?;
+            summary.written(sheet, output, rows);
+            summary.finish()?;
+            return Ok(());
+        }
+        #[cfg(feature = "parquet")]
+        Format::Parquet => {
+            write_batch_sheet(
+                "parquet",
+                &mut workbook,
+                &sheetnames,
+                &opt,
+                &mut summary,
+                |path, batch| columnar::write_parquet(path, batch).map_err(|e| e.to_string()),
+            )?;
+            return Ok(());
+        }
+        #[cfg(feature = "arrow")]
+        Format::Arrow => {
+            write_batch_sheet(
+                "arrow",
+                &mut workbook,
+                &sheetnames,
+                &opt,
+                &mut summary,
+                |path, batch| columnar::write_arrow(path, batch).map_err(|e| e.to_string()),
+            )?;
+            return Ok(());
+        }
+    }
+
+    if opt.concat {
+        let sheets = selected_or_filtered(&sheetnames, &opt)?;
+        let progress = start_progress(&mut workbook, &sheets, &opt);
+        let path = match opt.output.as_slice() {
+            [] => None,
+            [path] => Some(path.as_path()),
+            _ => {
+                return Err(usage_error(
+                    "--concat writes a single csv, give at most one output path",
+                )
+                .into())
+            }
+        };
+        let mut file = match path {
+            Some(path) => Some(OutputFile::new(create_output(path)?, path)),
+            None => None,
+        };
+        let sink: Box<dyn std::io::Write + '_> = match file.as_mut() {
+            Some(file) => Box::new(file),
+            None => Box::new(std::io::stdout()),
+        };
+        let sink = Encoded::new(sink, opt.encoding, path.is_some() && opt.bom)
+            .map_err(|e| format!("write {}: {}", display_output(path), e))?;
+        // sheets may have different widths
+        let mut wtr = csv_writer(&opt)?
+            .flexible(true)
+            .from_writer(RawLines::new(limit.wrap(sink)));
+        let mut has_header = false;
+        for sheet in sheets {
+            let with_header = match opt.concat_header {
+                ConcatHeader::First => !has_header,
+                ConcatHeader::All => true,
+                ConcatHeader::None => false,
+            };
+            let result = worksheet_to_csv(
+                &mut workbook,
+                sheet,
+                &mut wtr,
+                &opt,
+                None,
+                &progress,
+                with_header,
+            );
+            let rows = check_write(result, sheet, path, &mut summary)?;
+            has_header |= with_header && rows > 0;
+            summary.written(sheet, path, rows);
+        }
+        wtr.flush()
+            .map_err(|e| format!("write {}: {}", display_output(path), e))?;
+        drop(wtr);
+        if let Some(file) = file {
+            file.finish()
+                .map_err(|e| format!("write {}: {}", display_output(path), e))?;
+        }
+    } else if opt.use_sheet_names {
+        let ext = file_extension(&opt);
+        let workdir = opt.workdir.clone().unwrap_or_default();
+        let template = NameTemplate::new(&opt).map_err(usage_error)?;
+        let sheets = filter_sheets(&sheetnames, &opt)?;
+        let input_stem = match opt.xlsx.file_stem() {
+            Some(stem)
+                if opt.name_from_input_when_single
+                    && sheets.len() == 1
+                    && opt.xlsx != Path::new("-") =>
+            {
+                Some(stem.to_string_lossy())
+            }
+            _ => None,
+        };
+        if opt.dry_run {
+            dry_run_sheet_names(&sheetnames, &sheets, &template, input_stem.as_deref(), &opt);
+            return Ok(());
+        }
+        let progress = start_progress(&mut workbook, &sheets, &opt);
+        let mut archive = match &opt.zip {
+            Some(path) if path.exists() && !confirm_overwrite(path, &opt) => {
+                eprintln!("skip writing {}: exists", path.display());
+                for sheet in sheets {
+                    let reason = format!("{} exists", path.display());
+                    summary.add(sheet, Status::Skipped(reason), 0);
+                }
+                summary.finish()?;
+                return Ok(());
+            }
+            Some(path) => Some(zip::ZipWriter::new(create_output(path)?)),
+            None => None,
+        };
+        // sheets by the hash of their outputs, for `--dedup-sheets`
+        let mut seen: HashMap<u64, &String> = HashMap::new();
+        // filenames rendered so far, to keep sanitized names apart
+        let mut used = HashSet::new();
+        let jobs = write_jobs(&opt);
+        // sheets and files to write by `jobs` threads, after the checks of all sheets
+        let mut pending = Vec::new();
+        for sheet in sheets {
+            let index = sheet_index(&sheetnames, sheet);
+            let sheet_name = output_name(&sheetnames, sheet);
+            let name = input_stem.as_deref().unwrap_or(&sheet_name);
+            // errors of hashing sheets are reported by writing them below
+            if let Some(Ok(hash)) = opt
+                .dedup_sheets
+                .then(|| content_hash(&mut workbook, sheet, &opt))
+            {
+                match seen.get(&hash) {
+                    Some(first) => {
+                        eprintln!("skip sheet `{}`: duplicate of `{}`", sheet, first);
+                        let reason = format!("duplicate of `{}`", first);
+                        summary.add(sheet, Status::Skipped(reason), 0);
+                        continue;
+                    }
+                    None => {
+                        seen.insert(hash, sheet);
+                    }
+                }
+            }
+            let output = match template.render(&sheet_name, name, index, &ext, &mut used) {
+                Some(name) if archive.is_some() => PathBuf::from(name),
+                Some(name) => workdir.join(name),
+                None => {
+                    eprintln!("skip sheet `{}`: not matching --name-from-regex", sheet);
+                    let reason = "not matching --name-from-regex".to_string();
+                    summary.add(sheet, Status::Skipped(reason), 0);
+                    continue;
+                }
+            };
+            if let Some(archive) = archive.as_mut() {
+                let entry = zip_entry_name(&output);
+                let options = zip::write::SimpleFileOptions::default()
+                    .compression_method(zip::CompressionMethod::Deflated);
+                if let Err(e) = archive.start_file(entry.as_str(), options) {
+                    eprintln!("skip sheet `{}`: {}", sheet, e);
+                    summary.add(sheet, Status::Skipped(e.to_string()), 0);
+                    continue;
+                }
+                println!("{}", entry);
+                let result = Encoded::new(&mut *archive, opt.encoding, opt.bom)
+                    .map_err(csv::Error::from)
+                    .and_then(|entry| {
+                        let mut wtr =
+                            csv_writer(&opt)?.from_writer(RawLines::new(limit.wrap(entry)));
+                        write_meta_header(&mut wtr, &mut workbook, sheet, &opt)?;
+                        write_sheet(&mut workbook, sheet, &mut wtr, &opt, None, &progress)
+                    });
+                let rows = check_write(result, sheet, opt.zip.as_deref(), &mut summary)?;
+                summary.written_as(sheet, entry, rows);
+                continue;
+            }
+            if output.exists() && !confirm_overwrite(&output, &opt) {
+                eprintln!("skip sheet `{}`: {} exists", sheet, output.display());
+                let reason = format!("{} exists", output.display());
+                summary.add(sheet, Status::Skipped(reason), 0);
+                continue;
+            }
+            if let Some(parent) = output.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("create directory {}: {}", parent.display(), e))?;
+            }
+            if jobs > 1 {
+                pending.push((sheet, output));
+                continue;
+            }
+            println!("{}", output.display());
+            let file = create_output(&output)?;
+            let result =
+                write_sheet_file(&mut workbook, sheet, file, &output, &opt, &limit, &progress);
+            let rows = check_write(result, sheet, Some(&output), &mut summary)?;
+            summary.written(sheet, Some(&output), rows);
+        }
+        let results = write_sheet_files_parallel(&pending, jobs, &opt, &limit);
+        for ((sheet, output), result) in pending.iter().zip(results) {
+            let rows = check_write(result?, sheet, Some(output), &mut summary)?;
+            summary.written(sheet, Some(output), rows);
+        }
+        if let Some(archive) = archive {
+            archive.finish().map_err(|e| {
+                format!(
+                    "write zip archive {}: {}",
+                    display_output(opt.zip.as_deref()),
+                    e
+                )
+            })?;
+        }
+    } else if opt.output.is_empty() {
+        if opt.select.is_none() && opt.default_action == DefaultAction::Error {
+            return Err(usage_error(
+                "no sheet selected, use `-s`, `-u` or output paths (see --default-action)",
+            )
+            .into());
+        }
+        if opt.tee.is_some() {
+            let sheets = match &opt.select {
+                // invalid selectors are reported below
+                Some(select) => select
+                    .find_in(&sheetnames, opt.name_match())
+                    .map_or(1, |sheets| sheets.len()),
+                None if opt.long || opt.default_action == DefaultAction::All => {
+                    filter_sheets(&sheetnames, &opt)?.len()
+                }
+                None => 1,
+            };
+            if sheets > 1 {
+                return Err(
+                    usage_error("--tee only supports a single sheet, select it by `-s`").into(),
+                );
+            }
+        }
+        let tcp = match &opt.tcp {
+            Some(addr) => Some(
+                std::net::TcpStream::connect(addr)
+                    .map_err(|e| format!("connect to {}: {}", addr, e))?,
+            ),
+            None => None,
+        };
+        let stdout: Box<dyn std::io::Write> = match &tcp {
+            Some(stream) => Box::new(
+                stream
+                    .try_clone()
+                    .map_err(|e| format!("clone tcp stream: {}", e))?,
+            ),
+            None => Box::new(std::io::stdout()),
+        };
+        let stdout: Box<dyn std::io::Write> = match &opt.tee {
+            Some(path) => {
+                let file = create_output(path)?;
+                Box::new(tee::Tee::new(stdout, file))
+            }
+            None => stdout,
+        };
+        // a BOM on stdout only with `--encoding utf8-bom`
+        let stdout = Encoded::new(stdout, opt.encoding, false)
+            .map_err(|e| format!("write {}: {}", display_output(None), e))?;
+        // selected sheets may have different widths
+        let mut wtr = csv_writer(&opt)?
+            .flexible(true)
+            .from_writer(RawLines::new(limit.wrap(stdout)));
+        let paging = opt.page.map(|rows| Paging {
+            rows: rows.get(),
+            separator: opt.page_separator.0.clone(),
+            repeat_header: opt.page_header,
+        });
+
+        if let Some(select) = &opt.select {
+            let sheets = select
+                .find_in(&sheetnames, opt.name_match())
+                .map_err(usage_error)?;
+            let progress = start_progress(&mut workbook, &sheets, &opt);
+            if opt.long && sheets.len() > 1 {
+                let result = write_row(&mut wtr, ["sheet", "row", "col", "value"], &opt);
+                check_write(result, sheets[0], None, &mut summary)?;
+                for sheet in sheets {
+                    let name = output_name(&sheetnames, sheet);
+                    let result = worksheet_to_long(
+                        &mut workbook,
+                        sheet,
+                        &mut wtr,
+                        &opt,
+                        Some(&name),
+                        &progress,
+                    );
+                    let rows = check_write(result, sheet, None, &mut summary)?;
+                    summary.written(sheet, None, rows);
+                }
+            } else {
+                let banner = opt.sheet_banner && sheets.len() > 1;
+                for sheet in sheets {
+                    if banner {
+                        let result = write_banner(&mut wtr, &output_name(&sheetnames, sheet));
+                        check_write(result, sheet, None, &mut summary)?;
+                    }
+                    let result =
+                        write_meta_header(&mut wtr, &mut workbook, sheet, &opt).and_then(|_| {
+                            write_sheet(
+                                &mut workbook,
+                                sheet,
+                                &mut wtr,
+                                &opt,
+                                paging.as_ref(),
+                                &progress,
+                            )
+                        });
+                    let rows = check_write(result, sheet, None, &mut summary)?;
+                    summary.written(sheet, None, rows);
+                }
+            }
+        } else if opt.long {
+            let sheets = filter_sheets(&sheetnames, &opt)?;
+            let progress = start_progress(&mut workbook, &sheets, &opt);
+            let result = write_row(&mut wtr, ["sheet", "row", "col", "value"], &opt);
+            check_write(result, &sheetnames[0], None, &mut summary)?;
+            for sheet in sheets {
+                let name = output_name(&sheetnames, sheet);
+                let result =
+                    worksheet_to_long(&mut workbook, sheet, &mut wtr, &opt, Some(&name), &progress);
+                let rows = check_write(result, sheet, None, &mut summary)?;
+                summary.written(sheet, None, rows);
+            }
+        } else {
+            let sheets = match opt.default_action {
+                DefaultAction::All => filter_sheets(&sheetnames, &opt)?,
+                _ => vec![&sheetnames[0]],
+            };
+            let progress = start_progress(&mut workbook, &sheets, &opt);
+            let banner = opt.sheet_banner && sheets.len() > 1;
+            for sheet in sheets {
+                if banner {
+                    let result = write_banner(&mut wtr, &output_name(&sheetnames, sheet));
+                    check_write(result, sheet, None, &mut summary)?;
+                }
+                let result =
+                    write_meta_header(&mut wtr, &mut workbook, sheet, &opt).and_then(|_| {
+                        write_sheet(
+                            &mut workbook,
+                            sheet,
+                            &mut wtr,
+                            &opt,
+                            paging.as_ref(),
+                            &progress,
+                        )
+                    });
+                let rows = check_write(result, sheet, None, &mut summary)?;
+                summary.written(sheet, None, rows);
+            }
+        }
+        // flushed by the writer when dropped, then signal the end of data to the listener
+        drop(wtr);
+        if let Some(stream) = tcp {
+            stream
+                .shutdown(std::net::Shutdown::Write)
+                .map_err(|e| format!("shut down tcp stream: {}", e))?;
+        }
+    } else {
+        let outputs = pair_outputs(&sheetnames, &opt, &mut summary)?;
+        if opt.dry_run {
+            for (sheet, output) in &outputs {
+                let exists = if output.exists() { " (exists)" } else { "" };
+                println!("write `{}` to {}{}", sheet, output.display(), exists);
+            }
+            return Ok(());
+        }
+        let sheets: Vec<&String> = outputs.iter().map(|(sheet, _)| *sheet).collect();
+        let progress = start_progress(&mut workbook, &sheets, &opt);
+        for (sheet, output) in &outputs {
+            println!("{}", output.display());
+            let file = create_output(output)?;
+            let result =
+                write_sheet_file(&mut workbook, sheet, file, output, &opt, &limit, &progress);
+            let rows = check_write(result, sheet, Some(output), &mut summary)?;
+            summary.written(sheet, Some(output), rows);
+        }
+    }
+    summary.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(rows: &[&[Data]]) -> Range<Data> {
+        let width = rows.iter().map(|r| r.len()).max().unwrap_or(1) as u32;
+        let mut range = Range::new((0, 0), (rows.len() as u32 - 1, width - 1));
+        for (i, row) in rows.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                range.set_value((i as u32, j as u32), cell.clone());
+            }
+        }
+        range
+    }
+
+    fn text(s: &str) -> Data {
+        Data::String(s.to_string())
+    }
+
+    fn opt(args: &[&str]) -> Opt {
+        Opt::from_iter(["xlsx2csv", "input.xlsx"].iter().chain(args))
+    }
+
+    fn rows(range: &Range<Data>, opt: &Opt) -> Vec<Vec<Data>> {
+        data_rows(range, opt).map(<[Data]>::to_vec).collect()
+    }
+
+    #[test]
+    fn header_row_parses_numbers_and_auto() {
+        assert_eq!("auto".parse(), Ok(HeaderRow::Auto));
+        assert_eq!(
+            "3".parse(),
+            Ok(HeaderRow::Row(NonZeroUsize::new(3).unwrap()))
+        );
+        assert!("0".parse::<HeaderRow>().is_err());
+        assert!("x".parse::<HeaderRow>().is_err());
+    }
+
+    #[test]
+    fn detects_header_below_a_title() {
+        let range = range(&[
+            &[text("Sales report")],
+            &[Data::Empty],
+            &[text("id"), text("name"), text("score")],
+            &[Data::Float(1.0), text("a"), Data::Float(2.5)],
+        ]);
+        assert_eq!(detect_header_row(&range, 0), 2);
+    }
+
+    #[test]
+    fn detects_the_first_of_the_widest_text_rows() {
+        let range = range(&[&[text("name"), text("city")], &[text("a"), text("Paris")]]);
+        assert_eq!(detect_header_row(&range, 0), 0);
+    }
+
+    #[test]
+    fn detected_header_ignores_rows_with_numbers_and_skipped_columns() {
+        let range = range(&[
+            &[Data::Float(2024.0), text("a"), text("b"), text("c")],
+            &[text(" "), text("x"), text("y")],
+            &[Data::Float(1.0), text("id"), text("name"), text("score")],
+        ]);
+        assert_eq!(detect_header_row(&range, 0), 1);
+        assert_eq!(detect_header_row(&range, 1), 0);
+    }
+
+    #[test]
+    fn detected_header_falls_back_to_the_first_row() {
+        let range = range(&[&[Data::Float(1.0)], &[Data::Float(2.0)]]);
+        assert_eq!(detect_header_row(&range, 0), 0);
+    }
+
+    #[test]
+    fn empty_rows_are_blank_cells_only() {
+        assert!(is_empty_row(&[Data::Empty, Data::Empty], false));
+        assert!(is_empty_row(&[], false));
+        assert!(!is_empty_row(&[Data::Empty, Data::Float(0.0)], false));
+        assert!(!is_empty_row(&[text(" \t")], false));
+        assert!(is_empty_row(&[text(" \t"), text("\u{feff}")], true));
+        assert!(!is_empty_row(&[text(" x ")], true));
+    }
+
+    #[test]
+    fn skip_empty_rows_drops_blank_and_whitespace_rows() {
+        let range = range(&[
+            &[text("id")],
+            &[Data::Empty],
+            &[text("  ")],
+            &[Data::Float(1.0)],
+        ]);
+        assert_eq!(rows(&range, &opt(&[])).len(), 4);
+        assert_eq!(
+            rows(&range, &opt(&["--skip-empty-rows"])),
+            vec![vec![text("id")], vec![text("  ")], vec![Data::Float(1.0)]]
+        );
+        let opt = opt(&["--skip-empty-rows", "--whitespace-is-empty"]);
+        assert_eq!(
+            rows(&range, &opt),
+            vec![vec![text("id")], vec![Data::Float(1.0)]]
+        );
+    }
+
+    fn date(serial: f64, is_1904: bool) -> ExcelDateTime {
+        ExcelDateTime::new(serial, ExcelDateTimeType::DateTime, is_1904)
+    }
+
+    #[test]
+    fn date_system_parses_known_names() {
+        assert_eq!("auto".parse(), Ok(DateSystem::Auto));
+        assert_eq!("1900".parse(), Ok(DateSystem::Excel1900));
+        assert_eq!("1904".parse(), Ok(DateSystem::Excel1904));
+        let err = "1901".parse::<DateSystem>().unwrap_err();
+        assert!(err.contains("supports: 1900, 1904, auto"), "{}", err);
+    }
+
+    #[test]
+    fn dates_follow_the_date_system() {
+        let cell = date(44000.0, false);
+        assert_eq!(format_datetime(&cell, DateSystem::Auto, None), "2020-06-18");
+        assert_eq!(
+            format_datetime(&cell, DateSystem::Excel1904, None),
+            "2024-06-19"
+        );
+        // the detected 1904 system is kept by `auto` and overridden by `1900`
+        let cell = date(44000.0, true);
+        assert_eq!(format_datetime(&cell, DateSystem::Auto, None), "2024-06-19");
+        assert_eq!(
+            format_datetime(&cell, DateSystem::Excel1900, None),
+            "2020-06-18"
+        );
+    }
+
+    #[test]
+    fn dates_with_time_and_durations() {
+        let cell = date(44000.5, false);
+        assert_eq!(
+            format_datetime(&cell, DateSystem::Auto, None),
+            "2020-06-18T12:00:00"
+        );
+        let duration = ExcelDateTime::new(1.5, ExcelDateTimeType::TimeDelta, false);
+        assert_eq!(
+            format_datetime(&duration, DateSystem::Excel1904, None),
+            "1.5"
+        );
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn blank_sheet_names_are_named_by_id() {
+        let sheetnames = names(&["Data", " "]);
+        assert_eq!(output_name(&sheetnames, &sheetnames[0]), "Data");
+        assert_eq!(output_name(&sheetnames, &sheetnames[1]), "sheet_1");
+        let select = |s: &str| {
+            s.parse::<SheetSelector>()
+                .unwrap()
+                .find_in(&sheetnames, NameMatch::default())
+        };
+        assert_eq!(select("sheet_1"), Ok(&sheetnames[1]));
+        assert_eq!(select("1"), Ok(&sheetnames[1]));
+        let err = select(" ").unwrap_err();
+        assert!(err.contains("select the sheet by id"), "{}", err);
+    }
+
+    #[test]
+    fn repeated_sheet_names_keep_their_index() {
+        let sheetnames = names(&["", "Data", ""]);
+        assert_eq!(sheet_index(&sheetnames, &sheetnames[2]), 2);
+        assert_eq!(output_name(&sheetnames, &sheetnames[0]), "sheet_0");
+        assert_eq!(output_name(&sheetnames, &sheetnames[2]), "sheet_2");
+        // a copy of the name is found by value
+        assert_eq!(sheet_index(&sheetnames, &"Data".to_string()), 1);
+    }
+
+    #[test]
+    fn unescape_html_decodes_entities_of_strings_only() {
+        let unescape = opt(&["--unescape-html"]);
+        let cell = text("Caf&eacute; &amp; Bar &#233;&#xE9; &lt;b&gt;");
+        assert_eq!(format_cell(&cell, &unescape), "Café & Bar éé <b>");
+        let malformed = text("&bogus; &#xZZ; a&b");
+        assert_eq!(format_cell(&malformed, &unescape), "&bogus; &#xZZ; a&b");
+        assert_eq!(format_cell(&Data::Int(5), &unescape), "5");
+        assert_eq!(
+            format_cell(&cell, &opt(&[])),
+            "Caf&eacute; &amp; Bar &#233;&#xE9; &lt;b&gt;"
+        );
+    }
+
+    #[test]
+    fn xls_codepage_parses_numbers_and_labels() {
+        let codepage = |s: &str| s.parse::<XlsCodepage>().map(|cp| cp.0);
+        assert_eq!(codepage("1251"), Ok(1251));
+        assert_eq!(codepage("windows-1251"), Ok(1251));
+        assert_eq!(codepage(" shift_jis "), Ok(932));
+        assert!(codepage("1").unwrap_err().contains("unsupported code page"));
+        assert!(codepage("klingon")
+            .unwrap_err()
+            .contains("unknown encoding"));
+    }
+
+    #[test]
+    fn empty_header_name_renders_index_and_letter() {
+        let naming: EmptyHeaderName = "{letter}_{index}".parse().unwrap();
+        assert_eq!(naming.render(0, 0), "A_1");
+        // output columns are counted from the first column written, letters from the worksheet
+        assert_eq!(naming.render(1, 27), "AB_2");
+        assert_eq!(EmptyHeaderName::default().render(2, 2), "column_3");
+    }
+
+    #[test]
+    fn empty_header_name_rejects_unknown_placeholders() {
+        let err = "col_{name}".parse::<EmptyHeaderName>().unwrap_err();
+        assert!(err.contains("unknown placeholder"), "{}", err);
+        assert!("  ".parse::<EmptyHeaderName>().is_err());
+    }
+
+    #[test]
+    fn trim_trailing_rows_keeps_empty_rows_in_between() {
+        let range = range(&[
+            &[text("id")],
+            &[Data::Empty],
+            &[Data::Float(1.0)],
+            &[text(" ")],
+            &[Data::Empty],
+        ]);
+        let trimmed = vec![vec![text("id")], vec![Data::Empty], vec![Data::Float(1.0)]];
+        let with_blank = [trimmed.clone(), vec![vec![text(" ")]]].concat();
+        assert_eq!(rows(&range, &opt(&["--trim-trailing-rows"])), with_blank);
+        let opt = opt(&["--trim-trailing-rows", "--whitespace-is-empty"]);
+        assert_eq!(rows(&range, &opt), trimmed);
+        let blank = self::range(&[&[Data::Empty], &[Data::Empty]]);
+        assert!(rows(&blank, &opt).is_empty());
+    }
+
+    fn csv_row(record: &[&str], opt: &Opt) -> String {
+        let mut wtr = csv_writer(opt).unwrap().from_writer(Vec::new());
+        write_row(&mut wtr, record, opt).unwrap();
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn non_ascii_delimiter_is_an_error_of_csv_writers() {
+        let opt = opt(&["-d", "§", "--quote-cols", "0"]);
+        let err = csv_writer(&opt).unwrap_err();
+        assert!(err.to_string().contains("Could not convert '§'"), "{}", err);
+        let mut wtr = csv::Writer::from_writer(Vec::new());
+        assert!(write_row(&mut wtr, ["a"], &opt).is_err());
+    }
+
+    #[test]
+    fn column_list_parses_indexes() {
+        let cols: ColumnList = "0, 3".parse().unwrap();
+        assert_eq!(cols.0, vec![0, 3]);
+        assert!(cols.contains(3) && !cols.contains(1));
+        assert!("0,a".parse::<ColumnList>().is_err());
+    }
+
+    #[test]
+    fn quote_cols_quotes_given_columns_only() {
+        let opt = opt(&["--quote-cols", "0,2"]);
+        assert_eq!(csv_row(&["007", "a", "b"], &opt), "\"007\",a,\"b\"\n");
+        // other columns are still quoted when necessary
+        assert_eq!(
+            csv_row(&["1", "x,y", "say \"hi\""], &opt),
+            "\"1\",\"x,y\",\"say \"\"hi\"\"\"\n"
+        );
+        assert_eq!(csv_row(&[""], &opt), "\"\"\n");
+        let opt = self::opt(&["--quote-cols", "1", "--trailing-delimiter"]);
+        assert_eq!(csv_row(&["a", "b"], &opt), "a,\"b\",\n");
+    }
+
+    #[test]
+    fn unquote_strips_one_layer_of_matching_quotes() {
+        assert_eq!(unquote("\"x\""), "x");
+        assert_eq!(unquote("'a b'"), "a b");
+        assert_eq!(unquote("\"\"x\"\""), "\"x\"");
+        assert_eq!(unquote("\"x'"), "\"x'");
+        assert_eq!(unquote("\""), "\"");
+        assert_eq!(unquote("''"), "");
+    }
+
+    #[test]
+    fn unquote_values_applies_after_unescape_html() {
+        let cell = text("&quot;x&quot;");
+        assert_eq!(
+            format_cell(&cell, &opt(&["--unquote-values"])),
+            "&quot;x&quot;"
+        );
+        let opt = opt(&["--unquote-values", "--unescape-html"]);
+        assert_eq!(format_cell(&cell, &opt), "x");
+        assert_eq!(format_cell(&Data::Int(5), &opt), "5");
+    }
+
+    #[test]
+    fn cell_newlines_are_normalized_for_every_line_ending() {
+        let s = "a\r\nb\rc\nd\n\r";
+        assert_eq!(CellNewline::Lf.normalize(s), "a\nb\nc\nd\n\n");
+        assert_eq!(CellNewline::Crlf.normalize(s), "a\r\nb\r\nc\r\nd\r\n\r\n");
+        assert_eq!(CellNewline::Space.normalize(s), "a b c d  ");
+        assert_eq!(CellNewline::Remove.normalize(s), "abcd");
+        assert_eq!("crlf".parse(), Ok(CellNewline::Crlf));
+        assert!("cr".parse::<CellNewline>().is_err());
+    }
+
+    #[test]
+    fn normalize_cell_newlines_applies_to_string_cells() {
+        let opt = opt(&["--normalize-cell-newlines", "space"]);
+        assert_eq!(format_cell(&text("a\r\nb"), &opt), "a b");
+        assert_eq!(format_cell(&text("a\r\nb"), &self::opt(&[])), "a\r\nb");
+    }
+
+    #[test]
+    fn pad_numeric_parses_column_width_and_char() {
+        let pad: PadNumeric = "2:6".parse().unwrap();
+        assert_eq!((pad.column, pad.width, pad.fill), (2, 6, '0'));
+        let pad: PadNumeric = "0:4: ".parse().unwrap();
+        assert_eq!(pad.fill, ' ');
+        assert!("0".parse::<PadNumeric>().is_err());
+        assert!("x:4".parse::<PadNumeric>().is_err());
+        assert!("0:4:ab".parse::<PadNumeric>().is_err());
+    }
+
+    #[test]
+    fn pad_numeric_keeps_the_sign_in_front_of_zeros() {
+        let zeros: PadNumeric = "0:6".parse().unwrap();
+        assert_eq!(zeros.pad("42".to_string()), Ok("000042".to_string()));
+        assert_eq!(zeros.pad("-42".to_string()), Ok("-00042".to_string()));
+        let spaces: PadNumeric = "0:6: ".parse().unwrap();
+        assert_eq!(spaces.pad("-42".to_string()), Ok("   -42".to_string()));
+        assert_eq!(zeros.pad("1234567".to_string()), Err("1234567".to_string()));
+    }
+
+    #[test]
+    fn pad_overflow_errors_or_truncates_numbers_only() {
+        let opt = opt(&["--pad-numeric", "0:3"]);
+        assert_eq!(render_cell(0, &Data::Float(7.0), &opt).unwrap(), "007");
+        assert_eq!(render_cell(0, &text("7"), &opt).unwrap(), "7");
+        assert_eq!(render_cell(1, &Data::Int(7), &opt).unwrap(), "7");
+        let err = render_cell(0, &Data::Int(12345), &opt).unwrap_err();
+        assert!(
+            err.to_string().contains("wider than the pad width 3"),
+            "{}",
+            err
+        );
+        let opt = self::opt(&["--pad-numeric", "0:3", "--pad-overflow", "truncate"]);
+        assert_eq!(render_cell(0, &Data::Int(12345), &opt).unwrap(), "123");
+    }
+
+    #[test]
+    fn sheet_names_match_ignoring_case_only_with_ignore_case() {
+        let sheetnames = names(&["Sales", "sales", "Costs"]);
+        let select = |s: &str, ignore_case, first_match| {
+            let matching = NameMatch {
+                ignore_case,
+                first_match,
+            };
+            s.parse::<SheetSelector>()
+                .unwrap()
+                .find_in(&sheetnames, matching)
+        };
+        assert!(select("costs", false, false).is_err());
+        assert_eq!(select("costs", true, false), Ok(&sheetnames[2]));
+        // exact names are preferred to case-insensitive matches
+        assert_eq!(select("sales", true, false), Ok(&sheetnames[1]));
+        let err = select("SALES", true, false).unwrap_err();
+        assert!(err.contains("matches 2 sheets ignoring case"), "{}", err);
+        assert_eq!(select("SALES", true, true), Ok(&sheetnames[0]));
+    }
+}
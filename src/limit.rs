@@ -0,0 +1,104 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Total bytes limit shared by all outputs of a run.
+#[derive(Clone, Debug)]
+pub struct ByteLimit {
+    limit: u64,
+    written: Arc<AtomicU64>,
+}
+
+impl ByteLimit {
+    /// Create a limit of `limit` bytes, `None` for unlimited.
+    pub fn new(limit: Option<u64>) -> Self {
+        ByteLimit {
+            limit: limit.unwrap_or(u64::MAX),
+            written: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Wrap a writer to account its bytes to this limit.
+    pub fn wrap<W: Write>(&self, inner: W) -> LimitedWriter<W> {
+        LimitedWriter {
+            inner,
+            limit: self.clone(),
+        }
+    }
+}
+
+/// Writer that fails without writing anything once the shared limit would be exceeded.
+pub struct LimitedWriter<W> {
+    inner: W,
+    limit: ByteLimit,
+}
+
+impl<W> LimitedWriter<W> {
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for LimitedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len() as u64;
+        let limit = self.limit.limit;
+        // reserve the bytes before writing, so concurrent writers can't pass the limit together
+        self.limit
+            .written
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |written| {
+                written.checked_add(len).filter(|&total| total <= limit)
+            })
+            .map_err(|_| {
+                io::Error::other(format!("total output bytes limit {} exceeded", limit))
+            })?;
+        let result = self.inner.write(buf);
+        // release the unwritten part of the reservation
+        let unwritten = match &result {
+            Ok(n) => len - *n as u64,
+            Err(_) => len,
+        };
+        if unwritten > 0 {
+            self.limit.written.fetch_sub(unwritten, Ordering::SeqCst);
+        }
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fails_without_writing_past_the_limit() {
+        let limit = ByteLimit::new(Some(5));
+        let mut w = limit.wrap(Vec::new());
+        w.write_all(b"abc").unwrap();
+        assert!(w.write_all(b"def").is_err());
+        w.write_all(b"de").unwrap();
+        assert_eq!(w.into_inner(), b"abcde");
+    }
+
+    #[test]
+    fn concurrent_writers_share_the_limit() {
+        let limit = ByteLimit::new(Some(1000));
+        let totals: Vec<usize> = std::thread::scope(|s| {
+            let workers: Vec<_> = (0..8)
+                .map(|_| {
+                    let mut w = limit.wrap(Vec::new());
+                    s.spawn(move || {
+                        while w.write_all(&[0; 7]).is_ok() {}
+                        w.into_inner().len()
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+        let total: usize = totals.iter().sum();
+        assert!(total <= 1000 && total > 1000 - 7, "{}", total);
+    }
+}
@@ -0,0 +1,50 @@
+use std::io::{self, Write};
+
+use calamine::{Data, Range};
+use serde_json::{Map, Value};
+
+use crate::header::header_names;
+use crate::json::{to_json, typed_json};
+use crate::{data_rows, data_start, Opt};
+
+/// Write a sheet as a MessagePack array of rows, returns the number of rows written.
+///
+/// Rows are arrays of all rows including the header, or with `--records`, maps keyed by the first row.
+/// Values are typed the same way as the JSON output.
+pub fn write_sheet<W: Write>(mut wtr: W, range: &Range<Data>, opt: &Opt) -> io::Result<usize> {
+    let mut rows: Vec<&[Data]> = data_rows(range, opt).collect();
+    let keys = if opt.records && !rows.is_empty() {
+        let col0 = data_start(range, opt).1 as usize;
+        Some(header_names(rows.remove(0), col0, opt))
+    } else {
+        None
+    };
+    rmp::encode::write_array_len(&mut wtr, rows.len() as u32).map_err(io::Error::other)?;
+    for (i, row) in rows.iter().enumerate() {
+        let value = match &keys {
+            Some(keys) => Value::Object(
+                keys.iter()
+                    .enumerate()
+                    .map(|(col, key)| {
+                        let value = match row.get(col) {
+                            Some(c) => to_json(col, c, opt)?,
+                            None => Value::Null,
+                        };
+                        Ok((key.clone(), value))
+                    })
+                    .collect::<io::Result<Map<String, Value>>>()?,
+            ),
+            // the header row of arrays is not mapped by `--map-values`
+            None if i == 0 => Value::Array(row.iter().map(|c| typed_json(c, opt)).collect()),
+            None => Value::Array(
+                row.iter()
+                    .enumerate()
+                    .map(|(col, c)| to_json(col, c, opt))
+                    .collect::<io::Result<_>>()?,
+            ),
+        };
+        rmp_serde::encode::write(&mut wtr, &value).map_err(io::Error::other)?;
+    }
+    wtr.flush()?;
+    Ok(rows.len())
+}
@@ -0,0 +1,228 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use encoding_rs::{Encoder, EncoderResult, WINDOWS_1252};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Writer of an output file, gzip-compressed if the path ends with `.gz`.
+pub enum OutputFile {
+    Plain(File),
+    Gzip(Box<GzEncoder<File>>),
+}
+
+impl OutputFile {
+    pub fn new(file: File, path: &Path) -> Self {
+        if path.extension().is_some_and(|ext| ext == "gz") {
+            OutputFile::Gzip(Box::new(GzEncoder::new(file, Compression::default())))
+        } else {
+            OutputFile::Plain(file)
+        }
+    }
+
+    /// Flush all data, and write the gzip trailer, which is not done reliably on drop.
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            OutputFile::Plain(mut file) => file.flush(),
+            OutputFile::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputFile::Plain(file) => file.write(buf),
+            OutputFile::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputFile::Plain(file) => file.flush(),
+            OutputFile::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Writer inserting raw lines before the next bytes, for comment lines which must bypass csv quoting.
+///
+/// Lines are queued through a shared reference (like from `csv::Writer::get_ref`),
+/// so the csv writer should be flushed before to keep the order.
+pub struct RawLines<W> {
+    inner: W,
+    pending: RefCell<Vec<u8>>,
+}
+
+impl<W: Write> RawLines<W> {
+    pub fn new(inner: W) -> Self {
+        RawLines {
+            inner,
+            pending: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Queue a line, ended by `terminator`.
+    pub fn push_line(&self, line: &str, terminator: &str) {
+        let mut pending = self.pending.borrow_mut();
+        pending.extend_from_slice(line.as_bytes());
+        pending.extend_from_slice(terminator.as_bytes());
+    }
+
+    /// The inner writer, queued lines are expected to be flushed.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn write_pending(&mut self) -> io::Result<()> {
+        let pending = self.pending.get_mut();
+        if !pending.is_empty() {
+            self.inner.write_all(pending)?;
+            pending.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for RawLines<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_pending()?;
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_pending()?;
+        self.inner.flush()
+    }
+}
+
+/// Text encoding of csv outputs, see `--encoding`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputEncoding {
+    Utf8,
+    Utf8Bom,
+    Latin1,
+}
+
+impl std::str::FromStr for OutputEncoding {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "utf8" | "utf-8" => Ok(OutputEncoding::Utf8),
+            "utf8-bom" | "utf-8-bom" => Ok(OutputEncoding::Utf8Bom),
+            "latin1" | "windows-1252" | "cp1252" => Ok(OutputEncoding::Latin1),
+            s => Err(format!(
+                "unknown encoding `{}`, supports: utf8, utf8-bom, latin1",
+                s
+            )),
+        }
+    }
+}
+
+/// Writer of UTF-8 text in an output encoding, starting with a UTF-8 BOM if asked.
+///
+/// Latin-1 is encoded as Windows-1252 (a superset of it, as Excel does), characters out of it are
+/// replaced with `?`. Bytes could be split anywhere between writes, incomplete characters are kept
+/// until the rest comes.
+pub struct Encoded<W> {
+    inner: W,
+    encoder: Option<Encoder>,
+    pending: Vec<u8>,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> Encoded<W> {
+    /// Writer in `encoding`, with a BOM if `bom` or `encoding` is `utf8-bom`.
+    pub fn new(mut inner: W, encoding: OutputEncoding, bom: bool) -> io::Result<Self> {
+        if bom || encoding == OutputEncoding::Utf8Bom {
+            inner.write_all(UTF8_BOM)?;
+        }
+        let encoder = match encoding {
+            OutputEncoding::Latin1 => Some(WINDOWS_1252.new_encoder()),
+            _ => None,
+        };
+        Ok(Encoded {
+            inner,
+            encoder,
+            pending: Vec::new(),
+            buf: Vec::new(),
+        })
+    }
+
+    /// The inner writer, a trailing incomplete character is dropped.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for Encoded<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Encoded {
+            inner,
+            encoder,
+            pending,
+            buf: out,
+        } = self;
+        let encoder = match encoder {
+            Some(encoder) => encoder,
+            None => return inner.write(buf),
+        };
+        pending.extend_from_slice(buf);
+        let mut rest = &pending[..];
+        while !rest.is_empty() {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    encode(encoder, text, out);
+                    rest = &[];
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    encode(encoder, std::str::from_utf8(valid).expect("valid"), out);
+                    match e.error_len() {
+                        // invalid bytes, replaced as unmappable
+                        Some(len) => {
+                            out.push(b'?');
+                            rest = &after[len..];
+                        }
+                        // an incomplete character at the end, kept for the next write
+                        None => {
+                            rest = after;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        let consumed = pending.len() - rest.len();
+        pending.drain(..consumed);
+        inner.write_all(out)?;
+        out.clear();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Encode text to the end of `out`, unmappable characters are replaced with `?`.
+fn encode(encoder: &mut Encoder, mut text: &str, out: &mut Vec<u8>) {
+    loop {
+        let start = out.len();
+        // one byte per character for single-byte encodings
+        out.resize(start + text.len().max(16), 0);
+        let (result, read, written) =
+            encoder.encode_from_utf8_without_replacement(text, &mut out[start..], false);
+        out.truncate(start + written);
+        text = &text[read..];
+        match result {
+            EncoderResult::InputEmpty => return,
+            EncoderResult::OutputFull => {}
+            EncoderResult::Unmappable(_) => out.push(b'?'),
+        }
+    }
+}
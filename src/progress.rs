@@ -0,0 +1,118 @@
+use std::cell::RefCell;
+use std::io::Stderr;
+use std::time::Duration;
+
+use pbr::ProgressBar;
+
+struct State {
+    /// The bar of all sheets, or of the current sheet if `per_sheet`.
+    bar: Option<ProgressBar<Stderr>>,
+    sheets: Vec<(String, u64)>,
+    current: Option<usize>,
+    base: u64,
+    per_sheet: bool,
+}
+
+fn progress_bar(total: u64, message: &str) -> ProgressBar<Stderr> {
+    let mut bar = ProgressBar::on(std::io::stderr(), total);
+    bar.show_speed = true;
+    bar.show_time_left = true;
+    bar.message(message);
+    bar.set_max_refresh_rate(Some(Duration::from_millis(100)));
+    bar
+}
+
+/// Progress bar on stderr over the rows of all sheets of a run, with the rate and ETA,
+/// or a bar of each sheet in turn.
+///
+/// Disabled progress does nothing, so it could be passed around unconditionally.
+/// The bar is finished when dropped.
+pub struct Progress {
+    state: Option<RefCell<State>>,
+}
+
+impl Progress {
+    pub fn disabled() -> Self {
+        Progress { state: None }
+    }
+
+    /// Start progress over sheets of row totals, in the order they will be written.
+    pub fn new(sheets: Vec<(String, u64)>) -> Self {
+        let bar = progress_bar(sheets.iter().map(|(_, rows)| rows).sum(), "rows ");
+        Progress::with_state(Some(bar), sheets, false)
+    }
+
+    /// Start progress of each sheet of row totals, a bar named by the sheet is started by `start_sheet`
+    /// and left on its own line when finished.
+    pub fn per_sheet(sheets: Vec<(String, u64)>) -> Self {
+        Progress::with_state(None, sheets, true)
+    }
+
+    fn with_state(
+        bar: Option<ProgressBar<Stderr>>,
+        sheets: Vec<(String, u64)>,
+        per_sheet: bool,
+    ) -> Self {
+        Progress {
+            state: Some(RefCell::new(State {
+                bar,
+                sheets,
+                current: None,
+                base: 0,
+                per_sheet,
+            })),
+        }
+    }
+
+    /// Move to the next sheet of the name, sheets before it are counted as complete (or skipped).
+    pub fn start_sheet(&self, sheet: &str) {
+        if let Some(state) = &self.state {
+            let mut state = state.borrow_mut();
+            let from = state.current.map_or(0, |i| i + 1);
+            let next = match state.sheets[from..]
+                .iter()
+                .position(|(name, _)| name == sheet)
+            {
+                Some(i) => from + i,
+                // not planned, counted in the current sheet
+                None => return,
+            };
+            state.current = Some(next);
+            if state.per_sheet {
+                if let Some(bar) = state.bar.as_mut() {
+                    bar.finish_println("");
+                }
+                let message = format!("{} ({}/{}) ", sheet, next + 1, state.sheets.len());
+                state.bar = Some(progress_bar(state.sheets[next].1, &message));
+                return;
+            }
+            state.base = state.sheets[..next].iter().map(|(_, rows)| rows).sum();
+            let base = state.base;
+            if let Some(bar) = state.bar.as_mut() {
+                bar.set(base);
+            }
+        }
+    }
+
+    /// Set the rows processed of the current sheet, at most its total.
+    pub fn set_rows(&self, rows: usize) {
+        if let Some(state) = &self.state {
+            let mut state = state.borrow_mut();
+            let total = state.current.map_or(0, |i| state.sheets[i].1);
+            let position = state.base + (rows as u64).min(total);
+            if let Some(bar) = state.bar.as_mut() {
+                bar.set(position);
+            }
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if let Some(state) = &self.state {
+            if let Some(bar) = state.borrow_mut().bar.as_mut() {
+                bar.finish_println("");
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use regex::Regex;
+
+/// Document properties in `docProps/core.xml` of OOXML packages (.xlsx .xlsm .xlsb).
+const CORE_PROPERTIES: &[(&str, &str)] = &[
+    ("title", "dc:title"),
+    ("subject", "dc:subject"),
+    ("creator", "dc:creator"),
+    ("keywords", "cp:keywords"),
+    ("description", "dc:description"),
+    ("category", "cp:category"),
+    ("last_modified_by", "cp:lastModifiedBy"),
+    ("revision", "cp:revision"),
+    ("created", "dcterms:created"),
+    ("modified", "dcterms:modified"),
+];
+
+/// Document properties in `meta.xml` of OpenDocument packages (.ods).
+const ODS_PROPERTIES: &[(&str, &str)] = &[
+    ("title", "dc:title"),
+    ("subject", "dc:subject"),
+    ("creator", "meta:initial-creator"),
+    ("keywords", "meta:keyword"),
+    ("description", "dc:description"),
+    ("last_modified_by", "dc:creator"),
+    ("created", "meta:creation-date"),
+    ("modified", "dc:date"),
+];
+
+/// Read workbook properties (title, author, dates...) directly from the package.
+///
+/// Returns `Ok(None)` if the format has no such metadata, eg. legacy .xls files.
+pub fn read_properties(path: &Path) -> Result<Option<Vec<(&'static str, String)>>, String> {
+    let file = File::open(path).map_err(|e| format!("open {}: {}", path.display(), e))?;
+    let mut archive = match zip::ZipArchive::new(file) {
+        Ok(archive) => archive,
+        // not a zip package, no properties could be read
+        Err(_) => return Ok(None),
+    };
+    for (name, keys) in &[
+        ("docProps/core.xml", CORE_PROPERTIES),
+        ("meta.xml", ODS_PROPERTIES),
+    ] {
+        let mut xml = String::new();
+        match archive.by_name(name) {
+            Ok(mut entry) => entry
+                .read_to_string(&mut xml)
+                .map_err(|e| format!("read {}: {}", name, e))?,
+            Err(_) => continue,
+        };
+        return Ok(Some(extract(&xml, keys)));
+    }
+    Ok(None)
+}
+
+fn extract(xml: &str, keys: &[(&'static str, &str)]) -> Vec<(&'static str, String)> {
+    keys.iter()
+        .filter_map(|(key, tag)| {
+            let pattern = format!(r"(?s)<{0}(?:\s[^>]*)?>(.*?)</{0}>", regex::escape(tag));
+            let re = Regex::new(&pattern).expect("valid property pattern");
+            re.captures(xml)
+                .map(|caps| (*key, unescape(caps[1].trim())))
+        })
+        .collect()
+}
+
+/// Decode the predefined XML entities and numeric character references.
+fn unescape(s: &str) -> String {
+    let re = Regex::new(r"&(#x[0-9a-fA-F]+|#[0-9]+|lt|gt|amp|quot|apos);").unwrap();
+    re.replace_all(s, |caps: &regex::Captures| {
+        let entity = &caps[1];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16)
+                .ok()
+                .and_then(std::char::from_u32),
+            _ => entity[1..].parse().ok().and_then(std::char::from_u32),
+        };
+        c.map(|c| c.to_string())
+            .unwrap_or_else(|| caps[0].to_string())
+    })
+    .into_owned()
+}
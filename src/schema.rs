@@ -0,0 +1,109 @@
+use calamine::Data;
+
+/// Column type inferred from the cells of a column, for typed outputs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColumnType {
+    Boolean,
+    Integer,
+    Real,
+    Text,
+}
+
+impl ColumnType {
+    /// Type of a single cell, `None` for empty and error cells.
+    fn of(cell: &Data) -> Option<ColumnType> {
+        match *cell {
+            Data::Bool(_) => Some(ColumnType::Boolean),
+            Data::Int(_) => Some(ColumnType::Integer),
+            Data::Float(f) if f.fract() == 0.0 => Some(ColumnType::Integer),
+            Data::Float(_) => Some(ColumnType::Real),
+            Data::String(_) | Data::DateTime(_) | Data::DateTimeIso(_) | Data::DurationIso(_) => {
+                Some(ColumnType::Text)
+            }
+            _ => None,
+        }
+    }
+
+    /// The common type of two types, booleans and integers are widened to numbers.
+    fn merge(self, other: ColumnType) -> ColumnType {
+        use ColumnType::*;
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Boolean, Integer) | (Integer, Boolean) => Integer,
+            (Boolean, Real) | (Real, Boolean) | (Integer, Real) | (Real, Integer) => Real,
+            _ => Text,
+        }
+    }
+
+    /// Whether the cell could be stored in a column of this type, empty and error cells could.
+    #[cfg(any(feature = "parquet", feature = "arrow"))]
+    pub fn accepts(self, cell: &Data) -> bool {
+        ColumnType::of(cell).is_none_or(|t| self.merge(t) == self)
+    }
+}
+
+/// Infer the type of each column from the first `sample` data rows, or all if `None`.
+///
+/// Empty cells are ignored, a column without any value is text.
+pub fn infer_types(rows: &[&[Data]], width: usize, sample: Option<usize>) -> Vec<ColumnType> {
+    let sample = sample.unwrap_or(rows.len());
+    (0..width)
+        .map(|col| {
+            rows.iter()
+                .take(sample)
+                .filter_map(|row| row.get(col).and_then(ColumnType::of))
+                .fold(None, |acc: Option<ColumnType>, t| {
+                    Some(acc.map(|a| a.merge(t)).unwrap_or(t))
+                })
+                .unwrap_or(ColumnType::Text)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn types_are_widened_across_rows() {
+        let rows: [&[Data]; 3] = [
+            &[Data::Bool(true), Data::Int(1), Data::Empty],
+            &[
+                Data::Int(2),
+                Data::Float(2.5),
+                Data::Error(calamine::CellErrorType::NA),
+            ],
+            &[Data::Float(3.0), Data::String("x".to_string()), Data::Empty],
+        ];
+        assert_eq!(
+            infer_types(&rows, 4, None),
+            [
+                ColumnType::Integer,
+                ColumnType::Text,
+                ColumnType::Text,
+                ColumnType::Text
+            ]
+        );
+    }
+
+    #[test]
+    fn types_are_inferred_from_sampled_rows() {
+        let rows: [&[Data]; 3] = [
+            &[Data::Int(1)],
+            &[Data::Float(1.5)],
+            &[Data::String("x".to_string())],
+        ];
+        assert_eq!(infer_types(&rows, 1, Some(1)), [ColumnType::Integer]);
+        assert_eq!(infer_types(&rows, 1, Some(2)), [ColumnType::Real]);
+        assert_eq!(infer_types(&rows, 1, Some(10)), [ColumnType::Text]);
+    }
+
+    #[cfg(any(feature = "parquet", feature = "arrow"))]
+    #[test]
+    fn inferred_types_accept_narrower_values() {
+        assert!(ColumnType::Real.accepts(&Data::Int(1)));
+        assert!(ColumnType::Integer.accepts(&Data::Empty));
+        assert!(!ColumnType::Integer.accepts(&Data::Float(1.5)));
+        assert!(!ColumnType::Real.accepts(&Data::String("x".to_string())));
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+
+use calamine::{Data, Range};
+use rusqlite::types::Value;
+use rusqlite::Connection;
+
+use crate::ddl::{sanitize_identifier, Dialect};
+use crate::header::{dedup_names, strip_prefix};
+use crate::schema::{infer_types, ColumnType};
+use crate::{format_cell, Opt};
+
+fn to_value(cell: &Data, column_type: ColumnType, opt: &Opt) -> Value {
+    match (cell, column_type) {
+        (Data::Empty, _) | (Data::Error(_), _) => Value::Null,
+        (Data::Int(i), ColumnType::Integer) => Value::Integer(*i),
+        (Data::Float(f), ColumnType::Integer) => Value::Integer(*f as i64),
+        (Data::Bool(b), ColumnType::Boolean | ColumnType::Integer) => Value::Integer(*b as i64),
+        (Data::Int(i), ColumnType::Real) => Value::Real(*i as f64),
+        (Data::Bool(b), ColumnType::Real) => Value::Real(*b as i64 as f64),
+        (Data::Float(f), ColumnType::Real) => Value::Real(*f),
+        (cell, _) => Value::Text(format_cell(cell, opt)),
+    }
+}
+
+/// Writer of sheets to tables in a SQLite database.
+pub struct SqliteWriter {
+    conn: Connection,
+    tables: HashSet<String>,
+}
+
+impl SqliteWriter {
+    pub fn open(path: &std::path::Path) -> rusqlite::Result<Self> {
+        Ok(SqliteWriter {
+            conn: Connection::open(path)?,
+            tables: HashSet::new(),
+        })
+    }
+
+    /// Open a temporary in-memory database, eg. for benchmarks.
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Ok(SqliteWriter {
+            conn: Connection::open_in_memory()?,
+            tables: HashSet::new(),
+        })
+    }
+
+    /// Write a sheet to a new table of the sanitized sheet name, returns the table name and the rows inserted.
+    ///
+    /// The first row is used as column names, existing table of the same name is replaced.
+    pub fn write_sheet(
+        &mut self,
+        sheet: &str,
+        range: &Range<Data>,
+        opt: &Opt,
+    ) -> rusqlite::Result<(String, usize)> {
+        let table =
+            dedup_names(vec![sanitize_identifier(sheet, "sheet")], &mut self.tables).remove(0);
+        let mut rows = range.rows();
+        let naming = opt.empty_header_name.clone().unwrap_or_default();
+        let col0 = range.start().map_or(0, |(_, col)| col as usize);
+        let header: Vec<String> = match rows.next() {
+            Some(row) => row
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    let name = format_cell(c, opt);
+                    sanitize_identifier(strip_prefix(&name, opt), &naming.render(i, col0 + i))
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let columns = dedup_names(header, &mut HashSet::new());
+        let data: Vec<&[Data]> = rows.collect();
+        let types = infer_types(&data, columns.len(), opt.infer_rows.map(NonZeroUsize::get));
+
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            &format!("DROP TABLE IF EXISTS {}", Dialect::Sqlite.quote(&table)),
+            [],
+        )?;
+        if columns.is_empty() {
+            // sqlite tables need at least one column
+            tx.execute(
+                &format!("CREATE TABLE {} (_ TEXT)", Dialect::Sqlite.quote(&table)),
+                [],
+            )?;
+            tx.commit()?;
+            return Ok((table, 0));
+        }
+        let definitions: Vec<String> = columns
+            .iter()
+            .zip(&types)
+            .map(|(name, t)| {
+                format!(
+                    "{} {}",
+                    Dialect::Sqlite.quote(name),
+                    Dialect::Sqlite.column_type(*t)
+                )
+            })
+            .collect();
+        tx.execute(
+            &format!(
+                "CREATE TABLE {} ({})",
+                Dialect::Sqlite.quote(&table),
+                definitions.join(", ")
+            ),
+            [],
+        )?;
+        let mut inserted = 0;
+        {
+            let placeholders = vec!["?"; columns.len()].join(", ");
+            let mut stmt = tx.prepare(&format!(
+                "INSERT INTO {} VALUES ({})",
+                Dialect::Sqlite.quote(&table),
+                placeholders
+            ))?;
+            for row in data {
+                let values = types.iter().enumerate().map(|(col, t)| {
+                    row.get(col)
+                        .map(|c| to_value(c, *t, opt))
+                        .unwrap_or(Value::Null)
+                });
+                inserted += stmt.execute(rusqlite::params_from_iter(values))?;
+            }
+        }
+        tx.commit()?;
+        Ok((table, inserted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structopt::StructOpt;
+
+    #[test]
+    fn write_sheet_returns_the_rows_inserted() {
+        let opt = Opt::from_iter(["xlsx2csv", "input.xlsx"]);
+        let mut range = Range::new((0, 0), (2, 1));
+        range.set_value((0, 0), Data::String("id".to_string()));
+        range.set_value((0, 1), Data::String("name".to_string()));
+        range.set_value((1, 0), Data::Int(1));
+        range.set_value((2, 1), Data::String("b".to_string()));
+        let mut db = SqliteWriter::open_in_memory().unwrap();
+        let (table, rows) = db.write_sheet("Data", &range, &opt).unwrap();
+        assert_eq!((table.as_str(), rows), ("Data", 2));
+        let count: i64 = db
+            .conn
+            .query_row("SELECT count(*) FROM \"Data\"", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+        let (_, rows) = db.write_sheet("Empty", &Range::empty(), &opt).unwrap();
+        assert_eq!(rows, 0);
+    }
+}
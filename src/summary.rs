@@ -0,0 +1,134 @@
+use std::path::{Path, PathBuf};
+
+/// Result of writing a sheet.
+#[derive(Clone, Debug)]
+pub enum Status {
+    Ok,
+    Empty,
+    Skipped(String),
+    Error(String),
+}
+
+/// Per-sheet results of a run, printed as a table to stderr by `--summary`,
+/// and written sheets are listed in the `--index-file`.
+#[derive(Debug)]
+pub struct Summary {
+    enabled: bool,
+    index: Option<PathBuf>,
+    entries: Vec<(String, Status, usize, Option<String>)>,
+}
+
+impl Summary {
+    pub fn new(enabled: bool, index: Option<PathBuf>) -> Self {
+        Summary {
+            enabled,
+            index,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Record `rows` written for `sheet` to the output file or stdout, no rows is reported as empty.
+    pub fn written(&mut self, sheet: &str, output: Option<&Path>, rows: usize) {
+        let output = output.map_or("-".to_string(), |path| path.display().to_string());
+        self.written_as(sheet, output, rows);
+    }
+
+    /// Record `rows` written for `sheet` to the output named as in the index, like an archive entry.
+    pub fn written_as(&mut self, sheet: &str, output: String, rows: usize) {
+        let status = if rows == 0 { Status::Empty } else { Status::Ok };
+        self.entries
+            .push((sheet.to_string(), status, rows, Some(output)));
+    }
+
+    pub fn add(&mut self, sheet: &str, status: Status, rows: usize) {
+        self.entries.push((sheet.to_string(), status, rows, None));
+    }
+
+    /// End of the run, print the table and write the index file if enabled.
+    pub fn finish(&self) -> Result<(), String> {
+        self.print();
+        if let Some(path) = &self.index {
+            self.write_index(path)
+                .map_err(|e| format!("write index file {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Write `sheet,output_path,rows` of written sheets as csv.
+    fn write_index(&self, path: &Path) -> csv::Result<()> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(["sheet", "output_path", "rows"])?;
+        for (sheet, _, rows, output) in &self.entries {
+            if let Some(output) = output {
+                wtr.write_record([sheet, output, &rows.to_string()])?;
+            }
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    /// Print the aligned table to stderr if enabled.
+    fn print(&self) {
+        if !self.enabled {
+            return;
+        }
+        let rows: Vec<[String; 4]> = self
+            .entries
+            .iter()
+            .map(|(sheet, status, rows, _)| {
+                let (status, message) = match status {
+                    Status::Ok => ("ok", ""),
+                    Status::Empty => ("empty", ""),
+                    Status::Skipped(msg) => ("skipped", msg.as_str()),
+                    Status::Error(msg) => ("error", msg.as_str()),
+                };
+                [
+                    sheet.clone(),
+                    status.to_string(),
+                    rows.to_string(),
+                    message.to_string(),
+                ]
+            })
+            .collect();
+        print_table(&["sheet", "status", "rows", "message"], &rows, &[2]);
+    }
+}
+
+/// Print an aligned table to stderr, columns in `right` (eg. numbers) are right aligned.
+pub fn print_table<R: AsRef<[String]>>(header: &[&str], rows: &[R], right: &[usize]) {
+    for line in table_lines(header, rows, right) {
+        eprintln!("{}", line);
+    }
+}
+
+/// Lines of an aligned table, see `print_table`.
+pub fn table_lines<R: AsRef<[String]>>(
+    header: &[&str],
+    rows: &[R],
+    right: &[usize],
+) -> Vec<String> {
+    let header: Vec<String> = header.iter().map(|s| s.to_string()).collect();
+    let mut widths = vec![0; header.len()];
+    for row in std::iter::once(header.as_slice()).chain(rows.iter().map(AsRef::as_ref)) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut lines = Vec::new();
+    for row in std::iter::once(header.as_slice()).chain(rows.iter().map(AsRef::as_ref)) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (cell, width))| {
+                if right.contains(&i) {
+                    format!("{:>1$}", cell, width)
+                } else {
+                    format!("{:<1$}", cell, width)
+                }
+            })
+            .collect();
+        lines.push(line.join("  ").trim_end().to_string());
+    }
+    lines
+}
@@ -0,0 +1,414 @@
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::rc::Rc;
+
+use calamine::{Cell, Data, Ods, Range, Reader, SheetVisible, Sheets, Xls, XlsOptions, Xlsb, Xlsx};
+
+use crate::cell_range::CellRange;
+use crate::{column_name, MergedCells, OnParseError, Opt};
+
+/// Format of an input workbook.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputFormat {
+    Xlsx,
+    Xls,
+    Ods,
+    Xlsb,
+}
+
+impl std::str::FromStr for InputFormat {
+    type Err = String;
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "xlsx" | "xlsm" => Ok(InputFormat::Xlsx),
+            "xls" => Ok(InputFormat::Xls),
+            "ods" => Ok(InputFormat::Ods),
+            "xlsb" => Ok(InputFormat::Xlsb),
+            s => Err(format!(
+                "unsupported input format `{}`, supports: xlsx, xls, ods, xlsb",
+                s
+            )),
+        }
+    }
+}
+
+impl InputFormat {
+    /// Format by the file extension, as `open_workbook_auto`.
+    fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "xls" | "xla" => Some(InputFormat::Xls),
+            "xlsx" | "xlsm" | "xlam" => Some(InputFormat::Xlsx),
+            "xlsb" => Some(InputFormat::Xlsb),
+            "ods" => Some(InputFormat::Ods),
+            _ => None,
+        }
+    }
+}
+
+/// Bytes of a workbook, a file or stdin read into memory.
+enum Input {
+    File(BufReader<File>),
+    Memory(Cursor<Vec<u8>>),
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::File(file) => file.read(buf),
+            Input::Memory(cursor) => cursor.read(buf),
+        }
+    }
+}
+
+impl Input {
+    /// Detect the format by the content and rewind: OLE compound files are .xls,
+    /// zip packages by their workbook part.
+    fn sniff(&mut self) -> io::Result<Option<InputFormat>> {
+        if self.is_ole()? {
+            return Ok(Some(InputFormat::Xls));
+        }
+        let format = zip::ZipArchive::new(&mut *self).ok().and_then(|archive| {
+            let has = |name: &str| archive.file_names().any(|entry| entry == name);
+            if has("xl/workbook.xml") {
+                Some(InputFormat::Xlsx)
+            } else if has("xl/workbook.bin") {
+                Some(InputFormat::Xlsb)
+            } else if has("content.xml") {
+                Some(InputFormat::Ods)
+            } else {
+                None
+            }
+        });
+        self.rewind()?;
+        Ok(format)
+    }
+
+    /// Whether it's an OLE compound file by the magic bytes, and rewind.
+    fn is_ole(&mut self) -> io::Result<bool> {
+        let mut magic = [0; 8];
+        let is_ole = self.read_exact(&mut magic).is_ok()
+            && magic == [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+        self.rewind()?;
+        Ok(is_ole)
+    }
+
+    /// Whether it's an encrypted OOXML package, an OLE compound file with an `EncryptedPackage` stream,
+    /// and rewind. Password-protected .xlsx, .xlsm and .xlsb files are saved this way.
+    fn is_encrypted(&mut self) -> io::Result<bool> {
+        if !self.is_ole()? {
+            return Ok(false);
+        }
+        // stream names are UTF-16LE in the directory entries
+        let name: Vec<u8> = "EncryptedPackage"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let mut bytes = Vec::new();
+        self.read_to_end(&mut bytes)?;
+        self.rewind()?;
+        Ok(bytes.windows(name.len()).any(|window| window == name))
+    }
+}
+
+impl Seek for Input {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Input::File(file) => file.seek(pos),
+            Input::Memory(cursor) => cursor.seek(pos),
+        }
+    }
+}
+
+/// Opened workbook with the range of the last accessed sheet cached.
+///
+/// Operations on the same sheet in a row reuse the parsed range,
+/// only one range is kept so the memory is bounded by the largest sheet.
+pub struct Workbook {
+    sheets: Sheets<Input>,
+    cache: bool,
+    on_parse_error: OnParseError,
+    transpose: bool,
+    merged: MergedCells,
+    cells: Option<CellRange>,
+    last: Option<(String, Rc<Range<Data>>)>,
+}
+
+impl Workbook {
+    /// Open the input workbook of options, the range cache is disabled by `--no-cache`.
+    ///
+    /// The path `-` reads the whole workbook from stdin. The format is `--input-format` if set,
+    /// or by the file extension, or detected from the content of stdin.
+    /// Strings of .xls files are decoded with `--xls-codepage` if set, instead of the one declared in the file.
+    /// Sheets failed to parse are handled by `--on-parse-error`, see `range`.
+    /// Merged cells are handled by `--merged` first, then ranges are restricted to `--range` and
+    /// swapped rows for columns by `--transpose`, so all options apply after them.
+    pub fn open(opt: &Opt) -> Result<Self, calamine::Error> {
+        let path = opt.xlsx.as_path();
+        let mut input = if path == Path::new("-") {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            Input::Memory(Cursor::new(bytes))
+        } else {
+            Input::File(BufReader::new(File::open(path)?))
+        };
+        let hint = opt.input_format.or_else(|| InputFormat::from_path(path));
+        let format = match hint {
+            Some(format) => format,
+            // stdin and unknown extensions are detected from the content
+            None => input.sniff()?.ok_or(calamine::Error::Msg(
+                "cannot detect the workbook format, use --input-format",
+            ))?,
+        };
+        // .xls files are OLE compound files too, only checked if sniffed as one
+        if (hint.is_none() || format != InputFormat::Xls) && input.is_encrypted()? {
+            return Err(calamine::Error::Msg(
+                "workbook appears to be password-protected (encrypted), \
+                 save a copy without the password and convert it instead",
+            ));
+        }
+        let sheets = match format {
+            InputFormat::Xlsx => Sheets::Xlsx(Xlsx::new(input)?),
+            InputFormat::Xls => {
+                let mut options = XlsOptions::default();
+                options.force_codepage = opt.xls_codepage.map(|cp| cp.0);
+                Sheets::Xls(Xls::new_with_options(input, options)?)
+            }
+            InputFormat::Ods => Sheets::Ods(Ods::new(input)?),
+            InputFormat::Xlsb => Sheets::Xlsb(Xlsb::new(input)?),
+        };
+        Ok(Workbook {
+            sheets,
+            cache: !opt.no_cache,
+            on_parse_error: opt.on_parse_error,
+            transpose: opt.transpose,
+            merged: opt.merged,
+            cells: opt.range,
+            last: None,
+        })
+    }
+
+    /// Whether the workbook is a legacy .xls file.
+    pub fn is_xls(&self) -> bool {
+        matches!(self.sheets, Sheets::Xls(_))
+    }
+
+    pub fn sheet_names(&self) -> Vec<String> {
+        self.sheets.sheet_names()
+    }
+
+    /// Whether a sheet is visible, hidden or very hidden (only by VBA), without parsing it.
+    pub fn visibility(&self, sheet: &str) -> SheetVisible {
+        self.sheets
+            .sheets_metadata()
+            .iter()
+            .find(|s| s.name == sheet)
+            .map_or(SheetVisible::Visible, |s| s.visible)
+    }
+
+    /// Get the range of a sheet, parsed at most once for repeated operations on it.
+    ///
+    /// If the sheet fails to parse, the error is returned with `abort`, or is reported to stderr and replaced by
+    /// an empty range with `skip-sheet`, or by the cells read before the failure with `emit-partial`.
+    pub fn range(&mut self, sheet: &str) -> io::Result<Rc<Range<Data>>> {
+        match self.try_range(sheet) {
+            Ok(range) => Ok(range),
+            Err(e) => {
+                let range = self.on_error(sheet, e)?;
+                Ok(self.shape(sheet, range))
+            }
+        }
+    }
+
+    /// Get the range of a sheet as `range`, but a parse error is returned instead of handled.
+    fn try_range(&mut self, sheet: &str) -> Result<Rc<Range<Data>>, calamine::Error> {
+        if let Some((name, range)) = &self.last {
+            if name == sheet {
+                return Ok(Rc::clone(range));
+            }
+        }
+        // drop the cached range before parsing the next one
+        self.last = None;
+        let range = self.sheets.worksheet_range(sheet)?;
+        Ok(self.shape(sheet, range))
+    }
+
+    /// Handle merged cells, `--range` and `--transpose` of a parsed range, and cache it.
+    fn shape(&mut self, sheet: &str, mut range: Range<Data>) -> Rc<Range<Data>> {
+        if self.merged != MergedCells::Blank {
+            self.merge_cells(sheet, &mut range);
+        }
+        if let Some(cells) = &self.cells {
+            range = cells.restrict(&range);
+        }
+        let range = Rc::new(if self.transpose {
+            transpose(&range)
+        } else {
+            range
+        });
+        if self.cache {
+            self.last = Some((sheet.to_string(), Rc::clone(&range)));
+        }
+        range
+    }
+
+    /// Number of rows of a sheet, from the declared dimensions of .xlsx and .xlsb sheets without parsing,
+    /// or the parsed range for other formats and sheets without dimensions.
+    pub fn row_count(&mut self, sheet: &str) -> io::Result<u64> {
+        Ok(self.size(sheet)?.0 as u64)
+    }
+
+    /// Rows and columns of a sheet, from the declared dimensions as `row_count`.
+    pub fn size(&mut self, sheet: &str) -> io::Result<(usize, usize)> {
+        let dimensions = match &mut self.sheets {
+            Sheets::Xlsx(xlsx) => xlsx
+                .worksheet_cells_reader(sheet)
+                .ok()
+                .map(|reader| reader.dimensions()),
+            Sheets::Xlsb(xlsb) => xlsb
+                .worksheet_cells_reader(sheet)
+                .ok()
+                .map(|reader| reader.dimensions()),
+            _ => None,
+        };
+        let (start, end) = match dimensions {
+            // a missing dimension is reported as the single cell A1
+            Some(d) if d.end != (0, 0) => match &self.cells {
+                Some(cells) => match cells.clip(d.start, d.end) {
+                    Some(clipped) => clipped,
+                    None => return Ok((0, 0)),
+                },
+                None => (d.start, d.end),
+            },
+            _ => return Ok(self.range(sheet)?.get_size()),
+        };
+        let rows = (end.0 - start.0) as usize + 1;
+        let cols = (end.1 - start.1) as usize + 1;
+        if self.transpose {
+            Ok((cols, rows))
+        } else {
+            Ok((rows, cols))
+        }
+    }
+
+    /// Fill or warn the merged regions of a sheet by `--merged`.
+    fn merge_cells(&mut self, sheet: &str, range: &mut Range<Data>) {
+        let regions = match &mut self.sheets {
+            Sheets::Xlsx(xlsx) => xlsx
+                .merge_cells_by_sheet_name(sheet)
+                .map_err(|e| e.to_string()),
+            Sheets::Xls(xls) => xls
+                .merge_cells_by_sheet_name(sheet)
+                .map_err(|e| e.to_string()),
+            _ => Err("not supported for this format".to_string()),
+        };
+        let regions = match regions {
+            Ok(regions) => regions,
+            Err(e) => {
+                eprintln!("warning: merged cells of sheet `{}`: {}", sheet, e);
+                return;
+            }
+        };
+        if regions.is_empty() {
+            return;
+        }
+        if self.merged == MergedCells::Warn {
+            let a1 = |(row, col): (u32, u32)| format!("{}{}", column_name(col as usize), row + 1);
+            let refs: Vec<String> = regions
+                .iter()
+                .map(|d| format!("{}:{}", a1(d.start), a1(d.end)))
+                .collect();
+            eprintln!(
+                "warning: sheet `{}` has {} merged regions, values are only in their top-left cells: {}",
+                sheet,
+                regions.len(),
+                refs.join(", ")
+            );
+            return;
+        }
+        let end = range.end().unwrap_or_default();
+        for region in regions {
+            let value = match range.get_value(region.start) {
+                Some(value) if *value != Data::Empty => value.clone(),
+                _ => continue,
+            };
+            // cells out of the used range are left out, as blank ones are
+            for row in region.start.0..=region.end.0.min(end.0) {
+                for col in region.start.1..=region.end.1.min(end.1) {
+                    range.set_value((row, col), value.clone());
+                }
+            }
+        }
+    }
+
+    /// Handle a parse error of a sheet by `--on-parse-error`, the error is returned with `abort`.
+    fn on_error(&mut self, sheet: &str, error: calamine::Error) -> io::Result<Range<Data>> {
+        match self.on_parse_error {
+            OnParseError::Abort => {
+                return Err(io::Error::other(format!(
+                    "parse sheet `{}`: {} (use --on-parse-error to skip or salvage damaged sheets)",
+                    sheet, error
+                )));
+            }
+            OnParseError::SkipSheet => {
+                eprintln!("skip sheet `{}`: parse error: {}", sheet, error);
+            }
+            OnParseError::EmitPartial => match self.salvage(sheet) {
+                Some(range) => {
+                    let rows = range.end().map_or(0, |(row, _)| row + 1);
+                    eprintln!(
+                        "warning: parse sheet `{}`: {}, emit {} cells salvaged in the first {} rows",
+                        sheet,
+                        error,
+                        range.used_cells().count(),
+                        rows
+                    );
+                    return Ok(range);
+                }
+                None => eprintln!(
+                    "skip sheet `{}`: parse error: {}, nothing could be salvaged",
+                    sheet, error
+                ),
+            },
+        }
+        Ok(Range::empty())
+    }
+
+    /// Read the cells of a sheet until the first error, only supported by .xlsx and .xlsb files.
+    fn salvage(&mut self, sheet: &str) -> Option<Range<Data>> {
+        let mut cells = Vec::new();
+        match &mut self.sheets {
+            Sheets::Xlsx(xlsx) => {
+                let mut reader = xlsx.worksheet_cells_reader(sheet).ok()?;
+                while let Ok(Some(cell)) = reader.next_cell() {
+                    let value = Data::from(cell.get_value().clone());
+                    cells.push(Cell::new(cell.get_position(), value));
+                }
+            }
+            Sheets::Xlsb(xlsb) => {
+                let mut reader = xlsb.worksheet_cells_reader(sheet).ok()?;
+                while let Ok(Some(cell)) = reader.next_cell() {
+                    let value = Data::from(cell.get_value().clone());
+                    cells.push(Cell::new(cell.get_position(), value));
+                }
+            }
+            _ => return None,
+        }
+        Some(Range::from_sparse(cells))
+    }
+}
+
+/// Swap rows for columns of a range, the cell at `(row, col)` is moved to `(col, row)`.
+fn transpose(range: &Range<Data>) -> Range<Data> {
+    let (start, end) = match (range.start(), range.end()) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return Range::empty(),
+    };
+    let mut transposed = Range::new((start.1, start.0), (end.1, end.0));
+    for (row, col, cell) in range.used_cells() {
+        let (row, col) = (start.0 + row as u32, start.1 + col as u32);
+        transposed.set_value((col, row), cell.clone());
+    }
+    transposed
+}
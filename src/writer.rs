@@ -0,0 +1,14 @@
+use std::io;
+
+use calamine::{Data, Range};
+
+use crate::Opt;
+
+/// Writer of sheets into a single document, like an HTML page or a JSON array.
+pub trait SheetWriter {
+    /// Write a sheet named `sheet`, returns the number of rows written.
+    fn write_sheet(&mut self, sheet: &str, range: &Range<Data>, opt: &Opt) -> io::Result<usize>;
+
+    /// Close the document and flush it.
+    fn finish(self) -> io::Result<()>;
+}
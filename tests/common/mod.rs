@@ -0,0 +1,28 @@
+//! Helpers shared by the integration tests, each test file includes them by `mod common;`.
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Run the xlsx2csv binary with the arguments, paths are relative to the crate root.
+pub fn xlsx2csv(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_xlsx2csv"))
+        .args(args)
+        .output()
+        .expect("run xlsx2csv")
+}
+
+/// Stdout of xlsx2csv run with the arguments, which must succeed.
+pub fn stdout(args: &[&str]) -> String {
+    let output = xlsx2csv(args);
+    assert!(output.status.success(), "{:?}: {:?}", args, output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Empty output directory of a test, under the cargo target directory.
+pub fn workdir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use std::path::Path;
+
+use common::{workdir, xlsx2csv};
+
+fn files(dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn identical_sheets_are_written_once() {
+    // sheets A and B are identical, C differs by a cell
+    let dir = workdir("dedup_sheets");
+    let output = xlsx2csv(&[
+        "-u",
+        "tests/data/dups.xlsx",
+        "--dedup-sheets",
+        "--workdir",
+        dir.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("skip sheet `B`: duplicate of `A`"),
+        "{}",
+        stderr
+    );
+    assert_eq!(files(&dir), ["A.csv", "C.csv"]);
+    let a = std::fs::read_to_string(dir.join("A.csv")).unwrap();
+    assert_eq!(a, "id,name\n1,a\n");
+}
+
+#[test]
+fn identical_sheets_are_all_written_without_dedup() {
+    let dir = workdir("no_dedup_sheets");
+    let output = xlsx2csv(&[
+        "-u",
+        "tests/data/dups.xlsx",
+        "--workdir",
+        dir.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert_eq!(files(&dir), ["A.csv", "B.csv", "C.csv"]);
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+
+use common::workdir;
+
+fn rows(args: &[&str]) -> String {
+    common::stdout(&[&["tests/data/rows.xlsx"], args].concat())
+}
+
+fn gunzip(path: &Path) -> String {
+    let mut csv = String::new();
+    GzDecoder::new(std::fs::File::open(path).unwrap())
+        .read_to_string(&mut csv)
+        .unwrap();
+    csv
+}
+
+#[test]
+fn gz_output_reads_back_as_the_plain_output() {
+    let dir = workdir("gzip_output");
+    let (plain, gz) = (dir.join("rows.csv"), dir.join("rows.csv.gz"));
+    for path in [&plain, &gz] {
+        rows(&[path.to_str().unwrap()]);
+    }
+    let compressed = std::fs::read(&gz).unwrap();
+    assert_eq!(compressed[..2], [0x1f, 0x8b], "gzip magic bytes");
+    assert_eq!(gunzip(&gz), std::fs::read_to_string(&plain).unwrap());
+}
+
+#[test]
+fn gz_name_template_compresses_sheet_files() {
+    let dir = workdir("gzip_template");
+    let args = ["-u", "--name-template", "{name}.{ext}.gz", "--workdir"];
+    rows(&[&args[..], &[dir.to_str().unwrap()]].concat());
+    let files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(files, [dir.join("Data.csv.gz")]);
+    assert_eq!(gunzip(&files[0]), rows(&[]));
+}
@@ -0,0 +1,20 @@
+mod common;
+
+use common::xlsx2csv;
+
+#[test]
+fn raw_matches_golden_file() {
+    let output = xlsx2csv(&["tests/data/raw.xlsx", "--raw"]);
+    assert!(output.status.success());
+    let expected = std::fs::read_to_string("tests/data/raw.csv").unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+}
+
+#[test]
+fn raw_rejects_trim_and_quote_style() {
+    for args in [["--trim", "all"], ["--quote-style", "always"]] {
+        let output = xlsx2csv(&["tests/data/raw.xlsx", "--raw", args[0], args[1]]);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(output.stdout.is_empty());
+    }
+}
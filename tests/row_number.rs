@@ -0,0 +1,45 @@
+mod common;
+
+fn numbered(args: &[&str]) -> String {
+    let base = [
+        "tests/data/rows.xlsx",
+        "--with-row-number",
+        "--skip-empty-rows",
+    ];
+    common::stdout(&[&base, args].concat())
+}
+
+#[test]
+fn worksheet_basis_numbers_rows_by_position() {
+    // the empty third row is skipped, but still counted
+    assert_eq!(numbered(&[]), "1,id,name\n2,1,a\n4,2,b\n");
+    assert_eq!(
+        numbered(&["--row-number-start", "0"]),
+        "0,id,name\n1,1,a\n3,2,b\n"
+    );
+}
+
+#[test]
+fn data_basis_numbers_output_rows_below_the_header() {
+    let args = ["--row-number-basis", "data"];
+    assert_eq!(numbered(&args), "row,id,name\n1,1,a\n2,2,b\n");
+    let args = ["--row-number-basis", "data", "--row-number-start", "0"];
+    assert_eq!(numbered(&args), "row,id,name\n0,1,a\n1,2,b\n");
+}
+
+#[test]
+fn rejects_unknown_basis() {
+    let output = common::xlsx2csv(&[
+        "tests/data/rows.xlsx",
+        "--with-row-number",
+        "--row-number-basis",
+        "excel",
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("unknown row number basis `excel`"),
+        "{}",
+        stderr
+    );
+}
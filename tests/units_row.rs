@@ -0,0 +1,39 @@
+mod common;
+
+fn units(args: &[&str]) -> String {
+    common::stdout(&[&["tests/data/units.xlsx"], args].concat())
+}
+
+#[test]
+fn merge_folds_units_into_header_names() {
+    // the name column has no unit, and units are trimmed
+    assert_eq!(
+        units(&["--units-row", "merge"]),
+        "name,length (m),weight (kg)\na,1,2.5\n"
+    );
+    assert_eq!(
+        units(&["--units-row", "merge", "--units-join", "_"]),
+        "name,length_(m),weight_(kg)\na,1,2.5\n"
+    );
+}
+
+#[test]
+fn drop_removes_units_row_or_keeps_it_as_metadata() {
+    assert_eq!(
+        units(&["--units-row", "drop"]),
+        "name,length,weight\na,1,2.5\n"
+    );
+    // kept units are trimmed as merged ones, ` kg ` is written `kg`
+    assert_eq!(
+        units(&["--units-row", "drop", "--units-keep"]),
+        "#,m,kg\nname,length,weight\na,1,2.5\n"
+    );
+}
+
+#[test]
+fn units_row_is_not_a_data_row() {
+    assert_eq!(
+        units(&["--units-row", "merge", "--max-rows", "1"]),
+        "name,length (m),weight (kg)\na,1,2.5\n"
+    );
+}
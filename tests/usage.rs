@@ -0,0 +1,31 @@
+mod common;
+
+use common::xlsx2csv;
+
+/// Assert xlsx2csv exits with the usage error code for the arguments, without any output.
+fn assert_usage_error(args: &[&str]) {
+    let output = xlsx2csv(&[&["examples/test.xlsx"], args].concat());
+    assert_eq!(output.status.code(), Some(2), "{:?}: {:?}", args, output);
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn page_conflicts_with_output_files() {
+    let dir = common::workdir("usage_page");
+    assert_usage_error(&["--page", "2", "-u", "-w", dir.to_str().unwrap()]);
+    assert_usage_error(&["--page", "2", "out.csv"]);
+}
+
+#[test]
+fn dims_conflicts_with_output_files() {
+    let dir = common::workdir("usage_dims");
+    assert_usage_error(&["--dims", "-u", "-w", dir.to_str().unwrap()]);
+    assert_usage_error(&["--dims", "-l"]);
+}
+
+#[test]
+fn properties_and_list_conflict_with_output_files() {
+    let dir = common::workdir("usage_properties");
+    assert_usage_error(&["--properties", "-u", "-w", dir.to_str().unwrap()]);
+    assert_usage_error(&["-l", "-u", "-w", dir.to_str().unwrap()]);
+}
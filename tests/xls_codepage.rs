@@ -0,0 +1,24 @@
+mod common;
+
+use common::{stdout, xlsx2csv};
+
+// strings of the workbook are cp1251 bytes, but its CODEPAGE record declares 1252
+const XLS: &str = "tests/data/cp1251.xls";
+
+#[test]
+fn strings_are_decoded_with_the_declared_code_page() {
+    assert_eq!(stdout(&[XLS]), "name,city\nÈâàí,Ìîñêâà\nÎëüãà,Êè¿â\n");
+}
+
+#[test]
+fn xls_codepage_overrides_the_declared_code_page() {
+    let expected = "name,city\nИван,Москва\nОльга,Київ\n";
+    assert_eq!(stdout(&[XLS, "--xls-codepage", "1251"]), expected);
+    assert_eq!(stdout(&[XLS, "--xls-codepage", "windows-1251"]), expected);
+}
+
+#[test]
+fn xls_codepage_rejects_unknown_encodings() {
+    let output = xlsx2csv(&[XLS, "--xls-codepage", "klingon"]);
+    assert_eq!(output.status.code(), Some(2));
+}